@@ -0,0 +1,141 @@
+// times the engines against the stack interpreter: `cargo bench` (with `--features jit` for
+// the jit too).
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use uvm::core::{Engine, RunStatus, UVM};
+
+const RUNS: usize = 5;
+
+const PROGRAMS: [(&str, &str); 4] = [
+    (
+        "countdown",
+        "
+            push 1000000
+            push 1
+        .loop:
+            pop
+            push 1
+            sub
+            dup 0
+            jmpif loop
+            hlt
+        ",
+    ),
+    (
+        "fibonacci",
+        "
+            push 0
+            push 1
+            push 1000000
+            push 1
+        .loop:
+            pop
+            swp 2
+            dup 1
+            add
+            swp 2
+            push 1
+            sub
+            swp 1
+            swp 2
+            swp 1
+            dup 0
+            jmpif loop
+            hlt
+        ",
+    ),
+    (
+        "polynomial",
+        "
+            push 0
+            push 1000000
+            push 1
+        .loop:
+            pop
+            dup 0
+            dup 0
+            mul
+            push 3
+            mul
+            dup 1
+            push 2
+            mul
+            add
+            push 1
+            add
+            push 1000
+            div
+            swp 1
+            swp 2
+            add
+            swp 1
+            push 1
+            sub
+            dup 0
+            jmpif loop
+            hlt
+        ",
+    ),
+    (
+        "locals",
+        "
+            enter 1
+            push 1000000
+            push 1
+        .loop:
+            pop
+            dup 0
+            lload 0
+            add
+            lstore 0
+            push 1
+            sub
+            dup 0
+            jmpif loop
+            hlt
+        ",
+    ),
+];
+
+fn time(source: &str, engine: Engine) -> Duration {
+    let mut fastest = Duration::MAX;
+    for _ in 0..RUNS {
+        let mut vm = UVM::new();
+        vm.set_engine(engine);
+        vm.set_output(Box::new(io::sink()));
+        assert!(vm.load_program(source).is_none());
+        let start = Instant::now();
+        assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+        fastest = fastest.min(start.elapsed());
+    }
+    fastest
+}
+
+fn main() {
+    let mut engines = vec![("registers", Engine::Register)];
+    if cfg!(feature = "jit") {
+        engines.push(("jit", Engine::Jit));
+    }
+
+    print!("{:<12} {:>12}", "program", "interpreter");
+    for (name, _) in &engines {
+        print!(" {:>20}", name);
+    }
+    println!();
+    for (name, source) in PROGRAMS {
+        let interpreter = time(source, Engine::Interpreter);
+        print!("{:<12} {:>10.1}ms", name, interpreter.as_secs_f64() * 1e3);
+        for (_, engine) in &engines {
+            let duration = time(source, *engine);
+            print!(
+                " {:>10.1}ms ({:>4.1}x)",
+                duration.as_secs_f64() * 1e3,
+                interpreter.as_secs_f64() / duration.as_secs_f64()
+            );
+        }
+        println!();
+    }
+}
@@ -0,0 +1,205 @@
+// the checks behind the fuzz targets. tests/fuzz_corpus.rs runs the checked in corpus
+// through them as well, so they only use the uvm crate.
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use uvm::{
+    bytecode::Image,
+    core::{RunStatus, UVM},
+    instruction::{Instruction, InstructionType},
+    limits::VmLimits,
+};
+
+const LIMITS: VmLimits = VmLimits {
+    max_stack_depth: Some(4096),
+    max_call_depth: Some(256),
+    max_memory_bytes: Some(1 << 16),
+    max_instructions: Some(10_000),
+    max_output_bytes: Some(1 << 16),
+};
+
+// operands the instruction generator picks from, besides raw bits.
+const OPERANDS: [f64; 12] = [
+    0.,
+    1.,
+    2.,
+    3.,
+    -1.,
+    0.5,
+    -0.,
+    255.,
+    1e300,
+    f64::NAN,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+];
+
+// assembly source: assembling never panics, the program runs the same in every mode, and
+// its disassembly assembles back into the same program.
+pub fn check_source(data: &[u8]) {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+    let mut vm = UVM::new();
+    if vm.load_program(source).is_some() {
+        return;
+    }
+
+    let mut reassembled = UVM::new();
+    assert!(
+        reassembled.load_program(&vm.disassemble()).is_none(),
+        "disassembly does not assemble"
+    );
+    assert_eq!(
+        program_bits(reassembled.program()),
+        program_bits(vm.program())
+    );
+    assert_eq!(reassembled.memory(), vm.memory());
+
+    let bytecode = vm.to_bytecode();
+    check_modes(|| {
+        let mut vm = UVM::new();
+        vm.load_program(source);
+        vm
+    });
+    check_bytecode(&bytecode);
+}
+
+// bytecode: loading never panics, and whatever loads encodes back to the same bytes and runs
+// the same in every mode.
+pub fn check_bytecode(data: &[u8]) {
+    let mut vm = UVM::new();
+    if vm.load_bytecode(data).is_some() {
+        return;
+    }
+    let bytecode = vm.to_bytecode();
+    let mut reloaded = UVM::new();
+    assert_eq!(reloaded.load_bytecode(&bytecode), None);
+    assert_eq!(reloaded.to_bytecode(), bytecode);
+
+    check_modes(|| {
+        let mut vm = UVM::new();
+        vm.load_bytecode(data);
+        vm
+    });
+}
+
+// turns arbitrary bytes into a program, two or ten bytes per instruction: the opcode, a
+// selector for the operand and, for raw operands, its bits.
+pub fn check_instructions(data: &[u8]) {
+    let mut program = Vec::new();
+    let mut bytes = data.iter();
+    while let (Some(opcode), Some(selector)) = (bytes.next(), bytes.next()) {
+        let instruction_type = match InstructionType::from_opcode(opcode % 30) {
+            Some(instruction_type) => instruction_type,
+            None => continue,
+        };
+        let operand = match selector {
+            0..=127 if !instruction_type.has_operand() => None,
+            0..=127 => Some(OPERANDS[*selector as usize % OPERANDS.len()]),
+            128..=191 => Some((*selector - 128) as f64),
+            192..=254 => None,
+            255 => {
+                let mut bits = [0; 8];
+                for byte in bits.iter_mut() {
+                    *byte = *bytes.next().unwrap_or(&0);
+                }
+                Some(f64::from_le_bytes(bits))
+            }
+        };
+        program.push(Instruction::new(instruction_type, operand));
+    }
+
+    let image = Image {
+        program,
+        memory: b"fuzz\0".to_vec(),
+        ..Image::default()
+    };
+    check_bytecode(&image.encode());
+}
+
+// runs a program three ways (all at once, in slices and one step at a time) and checks
+// they agree and stay within the limits.
+fn check_modes(load: impl Fn() -> UVM) {
+    let whole = run(load(), |vm| vm.execute().map(|_| ()));
+    let sliced = run(load(), |vm| loop {
+        match vm.execute_for(7) {
+            Ok(RunStatus::Paused) => continue,
+            result => return result.map(|_| ()),
+        }
+    });
+    let stepped = run(load(), |vm| {
+        while !vm.is_halted() {
+            if let Some(context) = vm.step() {
+                return Err(context);
+            }
+        }
+        Ok(())
+    });
+    assert_eq!(whole, sliced);
+    assert_eq!(whole, stepped);
+}
+
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    result: String,
+    stack: Vec<u64>,
+    frames: usize,
+    output: Vec<u8>,
+    exit_code: u8,
+    instructions: u64,
+}
+
+fn run(mut vm: UVM, execute: impl Fn(&mut UVM) -> Result<(), uvm::trap::TrapContext>) -> Outcome {
+    let output = SharedBuffer::default();
+    vm.set_output(Box::new(output.clone()));
+    vm.set_limits(LIMITS);
+
+    // the debug form, because nan operands and thrown values do not compare equal.
+    let result = format!("{:?}", execute(&mut vm));
+    assert!(vm.stack().len() <= LIMITS.max_stack_depth.unwrap());
+    assert!(vm.output_bytes() <= LIMITS.max_output_bytes.unwrap());
+    assert!(vm.instructions_executed() <= LIMITS.max_instructions.unwrap());
+
+    let output = output.0.borrow().clone();
+    Outcome {
+        result,
+        stack: vm.stack().iter().map(|value| value.to_bits()).collect(),
+        frames: vm.frames().len(),
+        output,
+        exit_code: vm.exit_code(),
+        instructions: vm.instructions_executed(),
+    }
+}
+
+fn program_bits(program: &[Instruction]) -> Vec<(InstructionType, Option<u64>)> {
+    program
+        .iter()
+        .map(|instruction| {
+            (
+                instruction.instruction_type,
+                instruction.operand.map(f64::to_bits),
+            )
+        })
+        .collect()
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+// generated from the table of the api in tests/ffi.rs, run it with UVM_UPDATE_HEADER=1
+// after changing the api.
+#ifndef UVM_H
+#define UVM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define UVM_OK 0
+#define UVM_HALTED 1
+#define UVM_PAUSED 2
+#define UVM_CANCELLED 3
+#define UVM_TIMED_OUT 4
+#define UVM_TRAPPED (-1)
+#define UVM_ERROR (-2)
+
+// receives everything the program prints, `length` bytes of utf-8 at a time.
+typedef void (*UvmOutput)(void *user_data, const uint8_t *bytes, size_t length);
+
+typedef struct UvmVm UvmVm;
+
+// a new vm without a program, printing to stdout.
+UvmVm *uvm_new(void);
+
+// frees a vm; null is ignored.
+void uvm_free(UvmVm *vm);
+
+// replaces the vm's program (and everything it did) with bytecode, or with assembly when the
+// bytes do not start with the bytecode header, and returns UVM_OK or UVM_ERROR.
+int32_t uvm_load(UvmVm *vm, const uint8_t *bytes, size_t length);
+
+// executes a single instruction. returns UVM_HALTED once the program halted, UVM_PAUSED when
+// there is more to execute and UVM_TRAPPED when the instruction trapped.
+int32_t uvm_step(UvmVm *vm);
+
+// executes at most `budget` instructions, or until the program stops when it is 0. returns
+// UVM_HALTED, UVM_PAUSED, UVM_CANCELLED, UVM_TIMED_OUT or UVM_TRAPPED.
+int32_t uvm_run(UvmVm *vm, uint64_t budget);
+
+// the code given to `hlt`, once the program halted.
+int32_t uvm_exit_code(const UvmVm *vm);
+
+// copies up to `capacity` values from the bottom of the stack into `values` and returns the
+// number of values on the stack, so a null buffer asks for the size.
+size_t uvm_stack(const UvmVm *vm, double *values, size_t capacity);
+
+// sends the program's output to `output` instead of stdout, or back to stdout when it is null.
+void uvm_set_output(UvmVm *vm, UvmOutput output, void *user_data);
+
+// why the last call on the vm failed, or null when it did not.
+const char *uvm_last_error(const UvmVm *vm);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,213 @@
+use crate::{
+    error::{BytecodeError, OperandError},
+    global::Float,
+    instruction::{Instruction, InstructionType},
+    lexer::Value,
+    symbol::{Symbol, SymbolKind, Visibility},
+    trap::Trap,
+};
+
+// layout, all numbers little-endian:
+//   magic "UVMB", version u8
+//   instruction count u32, then per instruction an opcode u8 (with OPERAND_FLAG set when an
+//     f64 operand follows)
+//   memory length u32, then the bytes
+//   trap handler count u32, then per handler the trap code u8 and the instruction index u32
+//   symbol count u32, then per symbol the kind u8, visibility u8, line u32, the value
+//     (tag u8 0 and an i64, or tag 1 and an f64) and the name (length u32 and utf-8 bytes)
+// `callnative` operands are registry ids, so a host has to register the same natives in the
+// same order as when the program was assembled.
+pub const MAGIC: &[u8; 4] = b"UVMB";
+pub const VERSION: u8 = 1;
+const OPERAND_FLAG: u8 = 0x80;
+
+// everything a program needs to run, as produced by the assembler or read from bytecode.
+#[derive(Debug, Default)]
+pub struct Image {
+    pub program: Vec<Instruction>,
+    pub memory: Vec<u8>,
+    pub trap_handlers: Vec<(u8, usize)>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Image {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+
+        write_u32(&mut bytes, self.program.len());
+        for instruction in &self.program {
+            let opcode = instruction.instruction_type.opcode();
+            match instruction.operand {
+                Some(operand) => {
+                    bytes.push(opcode | OPERAND_FLAG);
+                    bytes.extend_from_slice(&operand.to_le_bytes());
+                }
+                None => bytes.push(opcode),
+            }
+        }
+
+        write_u32(&mut bytes, self.memory.len());
+        bytes.extend_from_slice(&self.memory);
+
+        write_u32(&mut bytes, self.trap_handlers.len());
+        for (code, handler) in &self.trap_handlers {
+            bytes.push(*code);
+            write_u32(&mut bytes, *handler);
+        }
+
+        write_u32(&mut bytes, self.symbols.len());
+        for symbol in &self.symbols {
+            bytes.push(match symbol.kind {
+                SymbolKind::CodeLabel => 0,
+                SymbolKind::DataLabel => 1,
+                SymbolKind::Constant => 2,
+            });
+            bytes.push(match symbol.visibility {
+                Visibility::Local => 0,
+                Visibility::Global => 1,
+                Visibility::Exported => 2,
+            });
+            write_u32(&mut bytes, symbol.line);
+            match symbol.value {
+                Value::Integer(value) => {
+                    bytes.push(0);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                Value::Float(value) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            write_u32(&mut bytes, symbol.name.len());
+            bytes.extend_from_slice(symbol.name.as_bytes());
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, BytecodeError> {
+        if !is_bytecode(bytes) {
+            return Err(BytecodeError::NotBytecode);
+        }
+        let mut reader = Reader {
+            bytes,
+            position: MAGIC.len(),
+        };
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(BytecodeError::UnsupportedVersion(version));
+        }
+
+        let mut image = Image::default();
+        for _ in 0..reader.u32()? {
+            let byte = reader.u8()?;
+            let instruction_type = InstructionType::from_opcode(byte & !OPERAND_FLAG)
+                .ok_or(BytecodeError::IllegalOpcode(byte))?;
+            let operand = if byte & OPERAND_FLAG != 0 {
+                Some(Float::from_le_bytes(reader.array()?))
+            } else {
+                None
+            };
+            image
+                .program
+                .push(Instruction::new(instruction_type, operand));
+        }
+        for (address, instruction) in image.program.iter().enumerate() {
+            if let Some(err) = instruction.validate(image.program.len()) {
+                return Err(BytecodeError::InvalidOperand(address, err));
+            }
+        }
+
+        let length = reader.u32()?;
+        image.memory = reader.take(length)?.to_vec();
+
+        for _ in 0..reader.u32()? {
+            let code = reader.u8()?;
+            if Trap::name_from_code(code).is_none() {
+                return Err(BytecodeError::IllegalTrapCode(code));
+            }
+            let handler = reader.u32()?;
+            if handler >= image.program.len() {
+                return Err(BytecodeError::InvalidTrapHandler(
+                    code,
+                    OperandError::TargetOutOfBounds(handler),
+                ));
+            }
+            image.trap_handlers.push((code, handler));
+        }
+
+        for _ in 0..reader.u32()? {
+            let kind = match reader.u8()? {
+                0 => SymbolKind::CodeLabel,
+                1 => SymbolKind::DataLabel,
+                2 => SymbolKind::Constant,
+                _ => return Err(BytecodeError::IllegalSymbol),
+            };
+            let visibility = match reader.u8()? {
+                0 => Visibility::Local,
+                1 => Visibility::Global,
+                2 => Visibility::Exported,
+                _ => return Err(BytecodeError::IllegalSymbol),
+            };
+            let line = reader.u32()?;
+            let value = match reader.u8()? {
+                0 => Value::Integer(i64::from_le_bytes(reader.array()?)),
+                1 => Value::Float(Float::from_le_bytes(reader.array()?)),
+                _ => return Err(BytecodeError::IllegalSymbol),
+            };
+            let length = reader.u32()?;
+            let name = String::from_utf8(reader.take(length)?.to_vec())
+                .map_err(|_| BytecodeError::IllegalSymbol)?;
+            image
+                .symbols
+                .push(Symbol::new(name, kind, value, line, visibility));
+        }
+
+        if reader.position != bytes.len() {
+            return Err(BytecodeError::TrailingBytes);
+        }
+        Ok(image)
+    }
+}
+
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], BytecodeError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .ok_or(BytecodeError::UnexpectedEnd)?;
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or(BytecodeError::UnexpectedEnd)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, BytecodeError> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+}
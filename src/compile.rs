@@ -0,0 +1,316 @@
+use std::fmt::Write;
+
+use crate::{
+    core::{index_operand, UVM},
+    error::CompileError,
+    global::Float,
+    instruction::{Instruction, InstructionType},
+    limits::VmLimits,
+    numeric::Numeric,
+    trap::{Trap, TrapPosition},
+};
+
+// what every compiled program starts with: the stacks, the instructions that are more than a
+// line of c and the number formatting of `out`, `outf` and `dmp`.
+const RUNTIME: &str = include_str!("compile_runtime.c");
+
+// translates the loaded program into a standalone c program that prints, traps and exits like
+// `uvm run` does. instructions are labelled by their index, so jumps and calls become `goto`s;
+// `ret` and `throw` continue at an address only known at run time, through a switch.
+pub fn to_c(vm: &UVM) -> Result<String, CompileError> {
+    if vm.numeric() != Numeric::Float {
+        return Err(CompileError::IntegerArithmetic);
+    }
+    if *vm.limits() != VmLimits::default() {
+        return Err(CompileError::Limits);
+    }
+    let program = vm.program();
+    if let Some(address) = program
+        .iter()
+        .position(|instruction| instruction.instruction_type == InstructionType::CallNative)
+    {
+        return Err(CompileError::NativeCall(address));
+    }
+
+    let mut handlers: Vec<(u8, usize)> = vm
+        .trap_handlers()
+        .iter()
+        .map(|(code, handler)| (*code, *handler))
+        .collect();
+    handlers.sort();
+
+    // addresses past the end are only reached by jumping there, which traps.
+    let mut addresses: Vec<usize> = (0..=program.len()).collect();
+    addresses.extend(program.iter().filter_map(target));
+    addresses.extend(handlers.iter().map(|(_, handler)| *handler));
+    addresses.sort();
+    addresses.dedup();
+
+    let mut c = String::from(RUNTIME);
+    // only `outs` reads memory.
+    let reads_memory = program
+        .iter()
+        .any(|instruction| instruction.instruction_type == InstructionType::OutputString);
+    if reads_memory {
+        c.push_str("\nstatic const unsigned char memory[] = {");
+        for (index, byte) in vm.memory().iter().enumerate() {
+            if index % 16 == 0 {
+                c.push_str("\n   ");
+            }
+            write!(c, " {},", byte).unwrap();
+        }
+        if vm.memory().is_empty() {
+            c.push('0');
+        }
+        writeln!(
+            c,
+            "\n}};\nstatic const size_t memory_length = {};",
+            vm.memory().len()
+        )
+        .unwrap();
+    }
+
+    let dispatches = program.iter().any(|instruction| {
+        matches!(
+            instruction.instruction_type,
+            InstructionType::Return | InstructionType::Throw
+        )
+    });
+    let branches = program
+        .iter()
+        .any(|instruction| instruction.instruction_type == InstructionType::JumpIf);
+
+    c.push_str("\nint main(void) {\n    size_t address;\n    int trap;\n");
+    if dispatches {
+        c.push_str("    size_t ip;\n");
+    }
+    if branches {
+        c.push_str("    int jump;\n");
+    }
+    c.push_str("    goto L0;\n");
+
+    if dispatches {
+        c.push_str("\ndispatch:\n    switch (ip) {\n");
+        for address in &addresses {
+            writeln!(c, "    case {}:\n        goto L{};", address, address).unwrap();
+        }
+        c.push_str("    default:\n        abort();\n    }\n");
+    }
+
+    // a label nothing jumps to would be a warning.
+    let mut labels: Vec<usize> = if dispatches {
+        addresses.clone()
+    } else {
+        let mut labels = vec![0];
+        labels.extend(program.iter().filter_map(target));
+        labels.extend(handlers.iter().map(|(_, handler)| *handler));
+        labels
+    };
+    labels.sort();
+    labels.dedup();
+
+    c.push('\n');
+    for (address, instruction) in program.iter().enumerate() {
+        if labels.binary_search(&address).is_ok() {
+            writeln!(c, "L{}: // {}", address, instruction).unwrap();
+        } else {
+            writeln!(c, "    // {}", instruction).unwrap();
+        }
+        c.push_str(&translate(address, instruction));
+    }
+    for address in addresses
+        .iter()
+        .filter(|address| **address >= program.len())
+    {
+        if labels.binary_search(address).is_ok() {
+            writeln!(c, "L{}:", address).unwrap();
+        }
+        c.push_str(&raise("INVALID_INSTRUCTION_POINTER", *address));
+    }
+    c.push_str("\ntrapped:\n    switch (trap) {\n");
+    for (code, handler) in &handlers {
+        writeln!(
+            c,
+            "    case {}: // {}\n        push((double)address);\n        push({});\n        goto L{};",
+            code,
+            Trap::name_from_code(*code).unwrap_or_default(),
+            code,
+            handler
+        )
+        .unwrap();
+    }
+    c.push_str("    default:\n        break;\n    }\n    switch (address) {\n");
+    for address in &addresses {
+        writeln!(
+            c,
+            "    case {}:\n        return fail(trap, \"{}\");",
+            address,
+            escape(&location(vm, *address))
+        )
+        .unwrap();
+    }
+    c.push_str("    default:\n        abort();\n    }\n}\n");
+    Ok(c)
+}
+
+// where an instruction continues when it is not the next one.
+fn target(instruction: &Instruction) -> Option<usize> {
+    match instruction.instruction_type {
+        InstructionType::Jump
+        | InstructionType::JumpIf
+        | InstructionType::Call
+        | InstructionType::Try => index_operand(instruction.operand),
+        _ => None,
+    }
+}
+
+// the c for one instruction. `address` and `trap` are set before going to `trapped`.
+fn translate(address: usize, instruction: &Instruction) -> String {
+    let operand = index_operand(instruction.operand);
+    match (instruction.instruction_type, operand) {
+        (InstructionType::Push, _) => match instruction.operand {
+            Some(value) => format!("    push({});\n", float_literal(value)),
+            None => raise("ILLEGAL_OPERAND", address),
+        },
+        (InstructionType::Pop, _) => check("op_pop()", address),
+
+        (InstructionType::Duplicate, Some(offset)) => {
+            check(&format!("op_dup({}u)", offset), address)
+        }
+        (InstructionType::Swap, Some(offset)) if offset > 0 => {
+            check(&format!("op_swp({}u)", offset), address)
+        }
+        (InstructionType::Duplicate | InstructionType::Swap, _) => {
+            raise("ILLEGAL_OPERAND", address)
+        }
+
+        (InstructionType::Jump, Some(target)) => format!("    goto L{};\n", target),
+        (InstructionType::JumpIf, Some(target)) => format!(
+            "{}    if (jump) goto L{};\n",
+            check("op_jmpif(&jump)", address),
+            target
+        ),
+        // the stack is checked before the operand.
+        (InstructionType::JumpIf, None) => format!(
+            "{}{}",
+            check("op_jmpif(&jump)", address),
+            raise("ILLEGAL_OPERAND", address)
+        ),
+        (InstructionType::Call, Some(target)) => {
+            format!("    op_call({}u);\n    goto L{};\n", address + 1, target)
+        }
+        (InstructionType::Return, _) => {
+            format!("{}    goto dispatch;\n", check("op_ret(&ip)", address))
+        }
+
+        (InstructionType::Enter, Some(locals)) => {
+            check(&format!("op_enter({}u)", locals), address)
+        }
+        (InstructionType::Leave, _) => check("op_leave()", address),
+        (InstructionType::LoadLocal, Some(index)) => {
+            check(&format!("op_local({}u, 0)", index), address)
+        }
+        (InstructionType::StoreLocal, Some(index)) => {
+            check(&format!("op_local({}u, 1)", index), address)
+        }
+        (InstructionType::LoadArgument, Some(index)) => {
+            check(&format!("op_aload({}u)", index), address)
+        }
+        // without a frame that is the trap, whatever the operand.
+        (
+            InstructionType::LoadLocal | InstructionType::StoreLocal | InstructionType::LoadArgument,
+            None,
+        ) => format!(
+            "    trap = frames_length == 0 ? INVALID_FRAME : ILLEGAL_OPERAND;\n    address = {};\n    goto trapped;\n",
+            address
+        ),
+
+        (InstructionType::Try, Some(handler)) => format!("    op_try({}u);\n", handler),
+        (InstructionType::EndTry, _) => check("op_endtry()", address),
+        (InstructionType::Throw, _) => {
+            format!("{}    goto dispatch;\n", check("op_throw(&ip)", address))
+        }
+
+        (InstructionType::Equal, _) => check("op_binary(EQUAL)", address),
+        (InstructionType::GreaterEqual, _) => check("op_binary(GREATER_EQUAL)", address),
+        (InstructionType::Not, _) => check("op_not()", address),
+        (InstructionType::Add, _) => check("op_binary(ADD)", address),
+        (InstructionType::Subtract, _) => check("op_binary(SUBTRACT)", address),
+        (InstructionType::Multiply, _) => check("op_binary(MULTIPLY)", address),
+        (InstructionType::Divide, _) => check("op_binary(DIVIDE)", address),
+
+        (InstructionType::Dump, _) => String::from("    op_dmp();\n"),
+        (InstructionType::Output, _) => check("op_out()", address),
+        (InstructionType::Outputf, _) => check("op_outf()", address),
+        (InstructionType::OutputString, _) => check("op_outs(memory, memory_length)", address),
+        (InstructionType::OutputCharacter, _) => check("op_outc()", address),
+
+        (InstructionType::Halt, _) => match instruction.operand {
+            None => String::from("    return 0;\n"),
+            Some(code) if (0. ..=255.).contains(&code) && code.fract() == 0. => {
+                format!("    return {};\n", code as u8)
+            }
+            Some(_) => raise("ILLEGAL_OPERAND", address),
+        },
+
+        (
+            InstructionType::Enter
+            | InstructionType::Jump
+            | InstructionType::Call
+            | InstructionType::Try
+            | InstructionType::CallNative,
+            _,
+        ) => raise("ILLEGAL_OPERAND", address),
+    }
+}
+
+// calls a function that returns a trap, or 0 when there was none.
+fn check(call: &str, address: usize) -> String {
+    format!(
+        "    if ((trap = {})) {{\n        address = {};\n        goto trapped;\n    }}\n",
+        call, address
+    )
+}
+
+fn raise(trap: &str, address: usize) -> String {
+    format!(
+        "    trap = {};\n    address = {};\n    goto trapped;\n",
+        trap, address
+    )
+}
+
+// what follows the trap's name when it is reported at the address, like ` at 3 (div) in main+2`.
+fn location(vm: &UVM, address: usize) -> String {
+    let location = vm.symbols().code_location(address);
+    TrapPosition {
+        instruction_pointer: address,
+        instruction: vm.program().get(address),
+        location: location.as_ref(),
+    }
+    .to_string()
+}
+
+// a c expression for exactly this double.
+fn float_literal(value: Float) -> String {
+    if value.is_finite() {
+        // debug formatting always has a `.` or an exponent, and reads back as the same value.
+        format!("{:?}", value)
+    } else {
+        format!("bits(0x{:016x}u)", value.to_bits())
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(byte as char);
+            }
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => write!(escaped, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,610 @@
+// the runtime every program compiled by `uvm compile --to c` starts with. it does what the
+// interpreter does for each instruction, down to the formatting of numbers, so a compiled
+// program prints exactly what the interpreter would.
+#include <math.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+// a program uses only some of the runtime, which is not worth a warning.
+#ifdef __GNUC__
+#define RUNTIME static __attribute__((unused))
+#else
+#define RUNTIME static
+#endif
+
+enum {
+    STACK_UNDERFLOW = 1,
+    DIVISION_BY_ZERO = 2,
+    INVALID_INSTRUCTION_POINTER = 3,
+    INVALID_MEMORY_ADDRESS = 4,
+    ILLEGAL_OPERATION = 5,
+    ILLEGAL_OPERAND = 6,
+    CALL_STACK_UNDERFLOW = 8,
+    UNCAUGHT_THROW = 9,
+    INVALID_FRAME = 10,
+    STACK_OVERFLOW = 11,
+};
+
+static const char *const trap_names[] = {
+    "",
+    "StackUnderflow",
+    "DivisionByZero",
+    "InvalidInstructionPointer",
+    "InvalidMemoryAddress",
+    "IllegalOperation",
+    "IllegalOperand",
+    "NativeError",
+    "CallStackUnderflow",
+    "UncaughtThrow",
+    "InvalidFrame",
+    "StackOverflow",
+};
+
+struct frame {
+    size_t base;
+    size_t locals;
+    size_t saved_frame_pointer;
+    size_t call_depth;
+};
+
+struct try_block {
+    size_t handler;
+    size_t stack_depth;
+    size_t call_depth;
+    size_t frame_depth;
+};
+
+static double *stack;
+static size_t stack_length, stack_capacity;
+static size_t *calls;
+static size_t calls_length, calls_capacity;
+static struct frame *frames;
+static size_t frames_length, frames_capacity;
+static struct try_block *tries;
+static size_t tries_length, tries_capacity;
+static size_t frame_pointer;
+// the value of a throw nothing caught.
+static double thrown;
+
+// makes room for `needed` items, returning 0 when there is no memory for them.
+RUNTIME int reserve(void **items, size_t *capacity, size_t needed, size_t size) {
+    if (needed <= *capacity) {
+        return 1;
+    }
+    size_t grown = *capacity * 2 > needed ? *capacity * 2 : needed;
+    if (grown > SIZE_MAX / size) {
+        return 0;
+    }
+    void *resized = realloc(*items, grown * size);
+    if (resized == NULL) {
+        return 0;
+    }
+    *items = resized;
+    *capacity = grown;
+    return 1;
+}
+
+// like a rust vec, running out of memory while growing is fatal.
+#define GROW(items, length, capacity, extra)                                      \
+    do {                                                                         \
+        if ((length) + (extra) < (length) ||                                     \
+            !reserve((void **)&(items), &(capacity), (length) + (extra), sizeof *(items))) { \
+            fputs("out of memory\n", stderr);                                    \
+            abort();                                                             \
+        }                                                                        \
+    } while (0)
+
+// a double that is no decimal literal, like nan or infinity.
+RUNTIME double bits(uint64_t bits) {
+    double value;
+    memcpy(&value, &bits, sizeof value);
+    return value;
+}
+
+RUNTIME void push(double value) {
+    GROW(stack, stack_length, stack_capacity, 1);
+    stack[stack_length++] = value;
+}
+
+RUNTIME void output(const char *text, size_t length) {
+    fwrite(text, 1, length, stdout);
+}
+
+// the shortest digits that read back as `value` (finite and positive), without a decimal
+// point, and the exponent of the first one.
+RUNTIME void shortest_digits(double value, char *digits, int *exponent) {
+    char buffer[40];
+    for (int precision = 0; precision < 17; precision++) {
+        snprintf(buffer, sizeof buffer, "%.*e", precision, value);
+        if (strtod(buffer, NULL) == value) {
+            break;
+        }
+    }
+    char *e = strchr(buffer, 'e');
+    size_t length = 0;
+    for (char *c = buffer; c < e; c++) {
+        if (*c != '.') {
+            digits[length++] = *c;
+        }
+    }
+    digits[length] = '\0';
+    *exponent = atoi(e + 1);
+}
+
+// writes the digits as a plain decimal number, never with an exponent.
+RUNTIME void positional(char *out, const char *digits, int exponent) {
+    int count = (int)strlen(digits);
+    if (exponent < 0) {
+        out += sprintf(out, "0.");
+        for (int i = 0; i < -exponent - 1; i++) {
+            *out++ = '0';
+        }
+        strcpy(out, digits);
+    } else if (exponent >= count - 1) {
+        out += sprintf(out, "%s", digits);
+        for (int i = 0; i < exponent - (count - 1); i++) {
+            *out++ = '0';
+        }
+        *out = '\0';
+    } else {
+        memcpy(out, digits, exponent + 1);
+        out[exponent + 1] = '.';
+        strcpy(out + exponent + 2, digits + exponent + 1);
+    }
+}
+
+// rust's `{}`: what `out` prints.
+RUNTIME void format_display(char *out, double value) {
+    if (isnan(value)) {
+        strcpy(out, "NaN");
+        return;
+    }
+    if (signbit(value)) {
+        *out++ = '-';
+        value = -value;
+    }
+    if (isinf(value)) {
+        strcpy(out, "inf");
+    } else if (value == 0) {
+        strcpy(out, "0");
+    } else {
+        char digits[20];
+        int exponent;
+        shortest_digits(value, digits, &exponent);
+        positional(out, digits, exponent);
+    }
+}
+
+// rust's `{:?}`: what `dmp` prints. like `{}`, but whole numbers get a `.0`, and very large
+// and very small ones an exponent.
+RUNTIME void format_debug(char *out, double value) {
+    if (isnan(value)) {
+        strcpy(out, "NaN");
+        return;
+    }
+    if (signbit(value)) {
+        *out++ = '-';
+        value = -value;
+    }
+    if (isinf(value)) {
+        strcpy(out, "inf");
+    } else if (value == 0) {
+        strcpy(out, "0.0");
+    } else if (value >= 1e-4 && value < 1e16) {
+        char digits[20];
+        int exponent;
+        shortest_digits(value, digits, &exponent);
+        positional(out, digits, exponent);
+        if (strchr(out, '.') == NULL) {
+            strcat(out, ".0");
+        }
+    } else {
+        char digits[20];
+        int exponent;
+        shortest_digits(value, digits, &exponent);
+        *out++ = digits[0];
+        if (digits[1] != '\0') {
+            out += sprintf(out, ".%s", digits + 1);
+        }
+        sprintf(out, "e%d", exponent);
+    }
+}
+
+// a number can take up to 330 characters written out in full.
+#define NUMBER_SIZE 400
+
+RUNTIME int op_pop(void) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    stack_length--;
+    return 0;
+}
+
+RUNTIME int op_dup(size_t offset) {
+    if (offset >= stack_length) {
+        return STACK_UNDERFLOW;
+    }
+    push(stack[stack_length - 1 - offset]);
+    return 0;
+}
+
+RUNTIME int op_swp(size_t offset) {
+    if (offset >= stack_length) {
+        return STACK_UNDERFLOW;
+    }
+    double top = stack[stack_length - 1];
+    stack[stack_length - 1] = stack[stack_length - 1 - offset];
+    stack[stack_length - 1 - offset] = top;
+    return 0;
+}
+
+enum { ADD, SUBTRACT, MULTIPLY, DIVIDE, EQUAL, GREATER_EQUAL };
+
+RUNTIME int op_binary(int operation) {
+    if (stack_length < 2) {
+        return STACK_UNDERFLOW;
+    }
+    double a = stack[stack_length - 2];
+    double b = stack[stack_length - 1];
+    double result;
+    switch (operation) {
+    case ADD:
+        result = a + b;
+        break;
+    case SUBTRACT:
+        result = a - b;
+        break;
+    case MULTIPLY:
+        result = a * b;
+        break;
+    case DIVIDE:
+        if (b == 0) {
+            return DIVISION_BY_ZERO;
+        }
+        result = a / b;
+        break;
+    case EQUAL:
+        result = a == b;
+        break;
+    default:
+        result = a >= b;
+        break;
+    }
+    stack_length -= 2;
+    push(result);
+    return 0;
+}
+
+RUNTIME int op_not(void) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    stack[stack_length - 1] = stack[stack_length - 1] != 0 ? 0 : 1;
+    return 0;
+}
+
+// sets `jump` when the value on top is not zero, leaving it on the stack.
+RUNTIME int op_jmpif(int *jump) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    *jump = stack[stack_length - 1] != 0;
+    return 0;
+}
+
+RUNTIME void op_call(size_t return_address) {
+    GROW(calls, calls_length, calls_capacity, 1);
+    calls[calls_length++] = return_address;
+}
+
+RUNTIME int op_ret(size_t *ip) {
+    if (calls_length == 0) {
+        return CALL_STACK_UNDERFLOW;
+    }
+    *ip = calls[--calls_length];
+    // try blocks and frames left open by the returning call are closed with it.
+    while (tries_length > 0 && tries[tries_length - 1].call_depth > calls_length) {
+        tries_length--;
+    }
+    while (frames_length > 0 && frames[frames_length - 1].call_depth > calls_length) {
+        frame_pointer = frames[--frames_length].saved_frame_pointer;
+    }
+    return 0;
+}
+
+RUNTIME int op_enter(size_t locals) {
+    if (locals > SIZE_MAX - stack_length ||
+        !reserve((void **)&stack, &stack_capacity, stack_length + locals, sizeof *stack)) {
+        return STACK_OVERFLOW;
+    }
+    GROW(frames, frames_length, frames_capacity, 1);
+    frames[frames_length++] = (struct frame){stack_length, locals, frame_pointer, calls_length};
+    frame_pointer = stack_length;
+    for (size_t i = 0; i < locals; i++) {
+        stack[stack_length++] = 0;
+    }
+    return 0;
+}
+
+RUNTIME int op_leave(void) {
+    if (frames_length == 0) {
+        return INVALID_FRAME;
+    }
+    struct frame frame = frames[--frames_length];
+    // drop the locals but keep whatever was pushed above them.
+    size_t end = frame.base + frame.locals < stack_length ? frame.base + frame.locals : stack_length;
+    size_t start = frame.base < end ? frame.base : end;
+    memmove(stack + start, stack + end, (stack_length - end) * sizeof *stack);
+    stack_length -= end - start;
+    frame_pointer = frame.saved_frame_pointer;
+    return 0;
+}
+
+RUNTIME int op_local(size_t index, int store) {
+    if (frames_length == 0) {
+        return INVALID_FRAME;
+    }
+    size_t locals = frames[frames_length - 1].locals;
+    if (index >= locals) {
+        return ILLEGAL_OPERAND;
+    }
+    index += frame_pointer;
+    if (index >= stack_length) {
+        return STACK_UNDERFLOW;
+    }
+    if (!store) {
+        push(stack[index]);
+    } else {
+        if (stack_length <= frame_pointer + locals) {
+            return STACK_UNDERFLOW;
+        }
+        stack[index] = stack[--stack_length];
+    }
+    return 0;
+}
+
+RUNTIME int op_aload(size_t index) {
+    if (frames_length == 0) {
+        return INVALID_FRAME;
+    }
+    if (index >= frame_pointer || frame_pointer - 1 - index >= stack_length) {
+        return STACK_UNDERFLOW;
+    }
+    push(stack[frame_pointer - 1 - index]);
+    return 0;
+}
+
+RUNTIME void op_try(size_t handler) {
+    GROW(tries, tries_length, tries_capacity, 1);
+    tries[tries_length++] = (struct try_block){handler, stack_length, calls_length, frames_length};
+}
+
+RUNTIME int op_endtry(void) {
+    if (tries_length == 0) {
+        return ILLEGAL_OPERATION;
+    }
+    tries_length--;
+    return 0;
+}
+
+RUNTIME int op_throw(size_t *ip) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    double value = stack[stack_length - 1];
+    if (tries_length == 0) {
+        thrown = value;
+        return UNCAUGHT_THROW;
+    }
+    // unwind to the state at `try`, then hand the thrown value to the handler.
+    struct try_block block = tries[--tries_length];
+    stack_length = block.stack_depth;
+    calls_length = block.call_depth;
+    while (frames_length > block.frame_depth) {
+        frame_pointer = frames[--frames_length].saved_frame_pointer;
+    }
+    push(value);
+    *ip = block.handler;
+    return 0;
+}
+
+RUNTIME int op_out(void) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    char text[NUMBER_SIZE];
+    format_display(text, stack[stack_length - 1]);
+    strcat(text, "\n");
+    output(text, strlen(text));
+    return 0;
+}
+
+RUNTIME int op_outf(void) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    double value = stack[stack_length - 1];
+    char text[NUMBER_SIZE];
+    if (isnan(value)) {
+        strcpy(text, "NaN\n");
+    } else {
+        snprintf(text, sizeof text, "%.15f\n", value);
+    }
+    output(text, strlen(text));
+    return 0;
+}
+
+RUNTIME void output_debug(double value) {
+    char text[NUMBER_SIZE];
+    format_debug(text, value);
+    output(text, strlen(text));
+}
+
+RUNTIME void op_dmp(void) {
+    if (stack_length == 0) {
+        output("stack: []\n", 10);
+    } else {
+        output("stack: [\n", 9);
+        for (size_t i = 0; i < stack_length; i++) {
+            output("    ", 4);
+            output_debug(stack[i]);
+            output(",\n", 2);
+        }
+        output("]\n", 2);
+    }
+    if (frames_length == 0) {
+        return;
+    }
+    output("frames:", 7);
+    for (size_t i = 0; i < frames_length; i++) {
+        struct frame frame = frames[i];
+        // values below the frame may have been popped, taking locals with them.
+        size_t end = frame.base + frame.locals < stack_length ? frame.base + frame.locals : stack_length;
+        size_t start = frame.base < end ? frame.base : end;
+        char text[80];
+        snprintf(text, sizeof text, "\n    #%zu base %zu: locals [", i, frame.base);
+        output(text, strlen(text));
+        for (size_t local = start; local < end; local++) {
+            if (local > start) {
+                output(", ", 2);
+            }
+            output_debug(stack[local]);
+        }
+        output("]", 1);
+    }
+    output("\n", 1);
+}
+
+RUNTIME void output_character(uint32_t code) {
+    char text[4];
+    size_t length;
+    if (code < 0x80) {
+        text[0] = (char)code;
+        length = 1;
+    } else if (code < 0x800) {
+        text[0] = (char)(0xc0 | code >> 6);
+        text[1] = (char)(0x80 | (code & 0x3f));
+        length = 2;
+    } else if (code < 0x10000) {
+        text[0] = (char)(0xe0 | code >> 12);
+        text[1] = (char)(0x80 | (code >> 6 & 0x3f));
+        text[2] = (char)(0x80 | (code & 0x3f));
+        length = 3;
+    } else {
+        text[0] = (char)(0xf0 | code >> 18);
+        text[1] = (char)(0x80 | (code >> 12 & 0x3f));
+        text[2] = (char)(0x80 | (code >> 6 & 0x3f));
+        text[3] = (char)(0x80 | (code & 0x3f));
+        length = 4;
+    }
+    output(text, length);
+}
+
+RUNTIME int op_outc(void) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    double code = stack[stack_length - 1];
+    if (code < 0 || code != floor(code) || code > 4294967295.0) {
+        return ILLEGAL_OPERAND;
+    }
+    // surrogates and anything past the last code point are not characters.
+    if ((code >= 0xd800 && code <= 0xdfff) || code > 0x10ffff) {
+        return ILLEGAL_OPERAND;
+    }
+    output_character((uint32_t)code);
+    return 0;
+}
+
+RUNTIME int is_continuation(const unsigned char *bytes, size_t index, size_t length) {
+    return index < length && (bytes[index] & 0xc0) == 0x80;
+}
+
+// writes the bytes as utf-8, replacing every invalid sequence with U+FFFD like rust's
+// `String::from_utf8_lossy`.
+RUNTIME void output_lossy(const unsigned char *bytes, size_t length) {
+    size_t i = 0;
+    while (i < length) {
+        size_t start = i;
+        unsigned char byte = bytes[i++];
+        int valid = 1;
+        if (byte >= 0x80) {
+            unsigned char next = i < length ? bytes[i] : 0;
+            if (byte >= 0xc2 && byte <= 0xdf) {
+                if (is_continuation(bytes, i, length)) {
+                    i++;
+                } else {
+                    valid = 0;
+                }
+            } else if (byte >= 0xe0 && byte <= 0xef) {
+                int second = i < length &&
+                             ((byte == 0xe0 && next >= 0xa0 && next <= 0xbf) ||
+                              (byte >= 0xe1 && byte <= 0xec && next >= 0x80 && next <= 0xbf) ||
+                              (byte == 0xed && next >= 0x80 && next <= 0x9f) ||
+                              (byte >= 0xee && next >= 0x80 && next <= 0xbf));
+                if (!second) {
+                    valid = 0;
+                } else if (is_continuation(bytes, ++i, length)) {
+                    i++;
+                } else {
+                    valid = 0;
+                }
+            } else if (byte >= 0xf0 && byte <= 0xf4) {
+                int second = i < length &&
+                             ((byte == 0xf0 && next >= 0x90 && next <= 0xbf) ||
+                              (byte >= 0xf1 && byte <= 0xf3 && next >= 0x80 && next <= 0xbf) ||
+                              (byte == 0xf4 && next >= 0x80 && next <= 0x8f));
+                if (!second) {
+                    valid = 0;
+                } else if (!is_continuation(bytes, ++i, length)) {
+                    valid = 0;
+                } else if (is_continuation(bytes, ++i, length)) {
+                    i++;
+                } else {
+                    valid = 0;
+                }
+            } else {
+                valid = 0;
+            }
+        }
+        if (valid) {
+            output((const char *)bytes + start, i - start);
+        } else {
+            output("\xef\xbf\xbd", 3);
+        }
+    }
+}
+
+RUNTIME int op_outs(const unsigned char *memory, size_t memory_length) {
+    if (stack_length == 0) {
+        return STACK_UNDERFLOW;
+    }
+    double address = stack[stack_length - 1];
+    if (address < 0 || address != floor(address) || address >= (double)memory_length) {
+        return INVALID_MEMORY_ADDRESS;
+    }
+    const unsigned char *start = memory + (size_t)address;
+    const unsigned char *end = memchr(start, 0, memory_length - (size_t)address);
+    if (end == NULL) {
+        return INVALID_MEMORY_ADDRESS;
+    }
+    output_lossy(start, (size_t)(end - start));
+    return 0;
+}
+
+// reports a trap nothing handled the way `uvm run` does and exits like it.
+RUNTIME int fail(int trap, const char *location) {
+    fflush(stdout);
+    if (trap == UNCAUGHT_THROW) {
+        char value[NUMBER_SIZE];
+        format_display(value, thrown);
+        fprintf(stderr, "Trap: UncaughtThrow: %s%s\n", value, location);
+    } else {
+        fprintf(stderr, "Trap: %s%s\n", trap_names[trap], location);
+    }
+    return 70;
+}
@@ -1,254 +1,1428 @@
-use std::{fs::read_to_string, process::exit};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{stdout, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+use crate::jit::{Code, Machine, STOP};
 use crate::{
-    error::{LexingError, ParsingError},
+    bytecode::Image,
+    error::{BytecodeError, Diagnostic, LexingError, LexingWarning, OperandError},
+    expression::{evaluate, evaluate_list},
     global::{Float, Integer},
     instruction::{Instruction, InstructionType},
-    label::{Label, LabelTable},
+    lexer::{is_reserved_name, tokenize_line, Token, Value},
+    limits::{Limit, VmLimits},
+    native::{Native, NativeRegistry},
+    numeric::{Numeric, Operation},
+    register::{Block, Translation},
+    symbol::{Symbol, SymbolKind, SymbolTable, Visibility},
+    trap::{Trap, TrapContext},
 };
 
+// an active `try` block: where to continue when something is thrown inside it,
+// and how deep the stacks were when it was entered.
+#[derive(Debug, Clone, Copy)]
+struct TryFrame {
+    handler: usize,
+    stack_depth: usize,
+    call_depth: usize,
+    frame_depth: usize,
+}
+
+// a frame opened by `enter`. its locals live on the stack from `base` on,
+// and the arguments pushed before the call sit right below `base`.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub base: usize,
+    pub locals: usize,
+    saved_frame_pointer: usize,
+    call_depth: usize,
+}
+
+// how often `execute` looks at the cancellation flag and the deadline, in instructions.
+const INTERRUPT_CHECK_INTERVAL: u64 = 1024;
+
+// why `execute` returned without a trap. after anything but `Halted` the vm stopped
+// between two instructions, and calling `execute` again resumes the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    // the exit code given to `hlt`.
+    Halted(u8),
+    Cancelled,
+    TimedOut,
+    // `execute_for` used up its instructions.
+    Paused,
+}
+
+// what `execute` runs the program with. the jit needs the `jit` feature on x86-64 linux, and
+// where it is not built the interpreter runs instead. `Register` runs the program translated
+// into register operations (see `register.rs`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Engine {
+    #[default]
+    Interpreter,
+    Jit,
+    Register,
+}
+
+// how many values the stack has room for beyond its length while compiled code runs.
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+const JIT_STACK_ROOM: usize = 256;
+
 pub struct UVM {
     stack: Vec<Float>,
+    // return addresses pushed by `call`.
+    call_stack: Vec<usize>,
+    try_stack: Vec<TryFrame>,
+    frames: Vec<Frame>,
+    frame_pointer: usize,
     program: Vec<Instruction>,
     instruction_pointer: usize,
-    label_table: LabelTable,
+    symbol_table: SymbolTable,
+    natives: NativeRegistry,
+    // trap code -> instruction index of the handler installed with `.trap`.
+    trap_handlers: HashMap<u8, usize>,
+    memory: Vec<u8>,
+    case_insensitive: bool,
+    max_errors: usize,
+    warnings: Vec<Diagnostic<LexingWarning>>,
+    limits: VmLimits,
+    numeric: Numeric,
+    engine: Engine,
+    // the program compiled by the jit, kept for the next `execute`.
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    jit: Option<Code>,
+    // the program translated for the register engine, kept like the jit's code.
+    translation: Option<Translation>,
+    instructions_executed: u64,
+    output_bytes: usize,
+    // where the output instructions write to, stdout by default.
+    output: Box<dyn Write>,
+    // set from another thread through `cancellation_handle` to stop `execute`.
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
     halt: bool,
+    exit_code: u8,
+}
+
+// bookkeeping that only lives while a program is being assembled.
+struct AssemblyState {
+    line: usize,
+    in_data_section: bool,
+    after_terminator: bool,
+    // the most recent global label, which local labels (`@name`) are scoped under.
+    scope: String,
+    referenced_names: HashSet<String>,
+    // (name, line) of every `.export`, applied once all symbols are known.
+    exports: Vec<(String, usize)>,
+    // (line, position) of every definition of each numeric label, in source order.
+    numeric_labels: HashMap<Integer, Vec<(usize, usize)>>,
+    fixups: Vec<Fixup>,
+    trap_handlers: Vec<PendingTrapHandler>,
+    // the line of every instruction assembled from this source, in order.
+    instruction_lines: Vec<usize>,
+}
+
+// an instruction operand that referred to a symbol not defined yet; it is evaluated
+// again once the whole source has been read.
+struct Fixup {
+    instruction: usize,
+    tokens: Vec<Token>,
+    scope: String,
+    line: usize,
+}
+
+// a `.trap` handler whose target is evaluated once all labels are known.
+struct PendingTrapHandler {
+    code: u8,
+    tokens: Vec<Token>,
+    scope: String,
+    line: usize,
+}
+
+impl Default for UVM {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UVM {
     pub fn new() -> Self {
         Self {
             stack: Vec::new(),
+            call_stack: Vec::new(),
+            try_stack: Vec::new(),
+            frames: Vec::new(),
+            frame_pointer: 0,
             program: Vec::new(),
             instruction_pointer: 0,
-            label_table: LabelTable::new(),
+            symbol_table: SymbolTable::new(),
+            natives: NativeRegistry::new(),
+            trap_handlers: HashMap::new(),
+            memory: Vec::new(),
+            case_insensitive: false,
+            max_errors: 20,
+            warnings: Vec::new(),
+            limits: VmLimits::default(),
+            numeric: Numeric::default(),
+            engine: Engine::default(),
+            #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+            jit: None,
+            translation: None,
+            instructions_executed: 0,
+            output_bytes: 0,
+            output: Box::new(stdout()),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: None,
             halt: false,
+            exit_code: 0,
         }
     }
 
-    pub fn emulate(&mut self, filepath: &str, limit: usize) {
-        if let Some(err) = self.load_program_from_file(filepath) {
-            eprintln!("LexingError: {:#?}", err);
-            std::process::exit(1);
-        };
-        for _ in 0..limit {
-            if self.halt {
-                break;
+    // executes the loaded program until it halts, hits a trap without a handler,
+    // is cancelled or runs past the deadline.
+    pub fn execute(&mut self) -> Result<RunStatus, TrapContext> {
+        self.execute_until(None)
+    }
+
+    // like `execute`, but pauses after executing `instructions` instructions.
+    pub fn execute_for(&mut self, instructions: u64) -> Result<RunStatus, TrapContext> {
+        self.execute_until(Some(instructions))
+    }
+
+    fn execute_until(&mut self, budget: Option<u64>) -> Result<RunStatus, TrapContext> {
+        #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+        if self.engine == Engine::Jit {
+            if let Some(code) = self.compiled() {
+                let result = self.execute_compiled(&code, budget);
+                self.jit = Some(code);
+                return result;
+            }
+        }
+        if self.engine == Engine::Register {
+            if let Some(translation) = self.translated() {
+                let result = self.execute_translated(&translation, budget);
+                self.translation = Some(translation);
+                return result;
+            }
+        }
+
+        let mut executed: u64 = 0;
+        while !self.halt {
+            if budget == Some(executed) {
+                return Ok(RunStatus::Paused);
             }
-            if let Some(err) = self.execute_instruction() {
-                eprintln!("ParsingError: {:#?}", err);
-                exit(1);
+            if executed.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+                if let Some(status) = self.interrupted() {
+                    return Ok(status);
+                }
+            }
+            executed += 1;
+
+            if let Some(context) = self.step() {
+                return Err(context);
             };
         }
+        Ok(RunStatus::Halted(self.exit_code))
     }
 
-    pub fn run(&mut self, filepath: &str) {
-        if let Some(err) = self.load_program_from_file(filepath) {
-            eprintln!("LexingError: {:#?}", err);
-            std::process::exit(1);
-        };
+    fn interrupted(&mut self) -> Option<RunStatus> {
+        // a cancellation is consumed here, so a later `execute` resumes the program.
+        if self.cancelled.swap(false, Ordering::Relaxed) {
+            return Some(RunStatus::Cancelled);
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Some(RunStatus::TimedOut);
+            }
+        }
+        None
+    }
+
+    // the register engine's translation of the loaded program, translated again when the
+    // program changed. None when it can not be translated.
+    fn translated(&mut self) -> Option<Translation> {
+        match self.translation.take() {
+            Some(translation) if translation.is_for(&self.program, self.numeric) => {
+                Some(translation)
+            }
+            _ => Translation::new(&self.program, self.numeric),
+        }
+    }
+
+    // like the interpreter's loop, but running a whole block at a time where one starts and
+    // nothing in it could trap or stop at a limit or an interrupt check.
+    fn execute_translated(
+        &mut self,
+        translation: &Translation,
+        budget: Option<u64>,
+    ) -> Result<RunStatus, TrapContext> {
+        let mut registers = vec![0.; translation.registers()];
+        let mut executed: u64 = 0;
         while !self.halt {
-            if let Some(err) = self.execute_instruction() {
-                eprintln!("ParsingError: {:#?}", err);
-                exit(1);
-            };
+            if budget == Some(executed) {
+                return Ok(RunStatus::Paused);
+            }
+            if executed.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+                if let Some(status) = self.interrupted() {
+                    return Ok(status);
+                }
+            }
+
+            let mut fuel = INTERRUPT_CHECK_INTERVAL - executed % INTERRUPT_CHECK_INTERVAL;
+            if let Some(budget) = budget {
+                fuel = fuel.min(budget - executed);
+            }
+            if let Some(max) = self.limits.max_instructions {
+                fuel = fuel.min(max.saturating_sub(self.instructions_executed));
+            }
+            let runs = translation
+                .block(self.instruction_pointer)
+                .filter(|block| block.length <= fuel && self.block_runs(block));
+            match runs {
+                Some(block) => {
+                    self.instruction_pointer = block.run(&mut self.stack, &mut registers);
+                    self.instructions_executed += block.length;
+                    executed += block.length;
+                }
+                None => {
+                    executed += 1;
+                    if let Some(context) = self.step() {
+                        return Err(context);
+                    }
+                }
+            }
         }
+        Ok(RunStatus::Halted(self.exit_code))
     }
 
-    fn load_program_from_file(&mut self, filepath: &str) -> Option<LexingError> {
-        let source = read_to_string(filepath).unwrap_or_else(|err| {
-            eprintln!("ERROR: {}", err);
-            exit(1);
-        });
-        let instructions = source.trim().split("\n");
-        let mut instruction_index = 0;
+    // whether none of the block's instructions would trap, which the interpreter checks
+    // instruction by instruction.
+    fn block_runs(&self, block: &Block) -> bool {
+        if self.stack.len() < block.needs {
+            return false;
+        }
+        if block.checks_divisor && self.stack[self.stack.len() - 1] == 0. {
+            return false;
+        }
+        match self.limits.max_stack_depth {
+            Some(max) => {
+                self.stack_usage() as isize + block.peak
+                    <= isize::try_from(max).unwrap_or(isize::MAX)
+            }
+            None => true,
+        }
+    }
+
+    // the jit's code for the loaded program, compiled again when the program changed. None
+    // when it can not be compiled.
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    fn compiled(&mut self) -> Option<Code> {
+        match self.jit.take() {
+            Some(code) if code.is_for(&self.program, self.numeric) => Some(code),
+            _ => Code::compile(&self.program, self.numeric),
+        }
+    }
 
-        for instruction in instructions {
-            let instruction: Vec<&str> = instruction.trim().split(" ").collect();
-            let instruction_len = instruction.len();
+    // like the interpreter's loop, but executing up to the next interrupt check at a time
+    // with compiled code.
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    fn execute_compiled(
+        &mut self,
+        code: &Code,
+        budget: Option<u64>,
+    ) -> Result<RunStatus, TrapContext> {
+        let mut executed: u64 = 0;
+        while !self.halt {
+            if budget == Some(executed) {
+                return Ok(RunStatus::Paused);
+            }
+            if executed.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+                if let Some(status) = self.interrupted() {
+                    return Ok(status);
+                }
+            }
 
-            if instruction_len > 1 && instruction[0].starts_with(";") {
+            let mut fuel = INTERRUPT_CHECK_INTERVAL - executed % INTERRUPT_CHECK_INTERVAL;
+            if let Some(budget) = budget {
+                fuel = fuel.min(budget - executed);
+            }
+            if let Some(max) = self.limits.max_instructions {
+                fuel = fuel.min(max.saturating_sub(self.instructions_executed));
+            }
+            if fuel == 0 {
+                executed += 1;
+                if let Some(context) = self.step() {
+                    return Err(context);
+                }
                 continue;
             }
 
-            match instruction_len {
-                1 => {
-                    let operation = instruction[0].trim();
+            let (used, trap) = self.run_compiled(code, fuel);
+            executed += used;
+            if let Some(context) = trap {
+                return Err(context);
+            }
+        }
+        Ok(RunStatus::Halted(self.exit_code))
+    }
+
+    // runs the code for at most `fuel` instructions, stepping the ones it leaves to the
+    // interpreter. returns how many were executed and the trap that stopped it.
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    fn run_compiled(&mut self, code: &Code, fuel: u64) -> (u64, Option<TrapContext>) {
+        self.stack.reserve(JIT_STACK_ROOM);
+        let room = self.stack_room();
+        let mut machine = Machine::new(&mut self.stack, room, fuel);
+        // the fuel when the vm last caught up with the code.
+        let mut synced = fuel;
+        let mut trap = None;
+        let address = self.instruction_pointer;
+
+        code.run(&mut machine, address, |machine, address| {
+            // the code only pushed within the stack's capacity.
+            unsafe { self.stack.set_len(machine.length) };
+            self.instructions_executed += synced - machine.fuel;
+            self.instruction_pointer = address;
+            if machine.fuel == 0 {
+                synced = 0;
+                return STOP;
+            }
+
+            machine.fuel -= 1;
+            trap = self.step();
+            self.stack.reserve(JIT_STACK_ROOM);
+            machine.stack = self.stack.as_mut_ptr();
+            machine.length = self.stack.len();
+            machine.room = self.stack_room();
+            synced = machine.fuel;
+            if trap.is_some() || self.halt {
+                STOP
+            } else {
+                self.instruction_pointer as u64
+            }
+        });
+        (fuel - machine.fuel, trap)
+    }
+
+    // how long the stack may get without growing or running into the stack limit.
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    fn stack_room(&self) -> usize {
+        match self.limits.max_stack_depth {
+            Some(max) => max
+                .saturating_sub(self.frames.len() + self.try_stack.len())
+                .min(self.stack.capacity()),
+            None => self.stack.capacity(),
+        }
+    }
+
+    // a flag that stops `execute` with `RunStatus::Cancelled` soon after it is set.
+    pub fn cancellation_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.cancelled)
+    }
+
+    // `execute` stops with `RunStatus::TimedOut` once the deadline has passed.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    // executes a single instruction. when it traps and the program installed a handler for
+    // the trap, the faulting instruction's index and the trap code are pushed (code on top)
+    // and execution continues at the handler. otherwise the instruction pointer is left on
+    // the faulting instruction and the trap is returned.
+    pub fn step(&mut self) -> Option<TrapContext> {
+        let address = self.instruction_pointer;
+        let mut trap = match self.check_limits() {
+            Some(trap) => trap,
+            None => {
+                self.instructions_executed += 1;
+                self.execute_instruction()?
+            }
+        };
+
+        if let Some(handler) = self.trap_handlers.get(&trap.code()).copied() {
+            if self.fits_on_stack(2) {
+                self.stack.push(address as Float);
+                self.stack.push(trap.code() as Float);
+                self.instruction_pointer = handler;
+                return None;
+            }
+            trap = Trap::StackOverflow;
+        }
+
+        self.instruction_pointer = address;
+        let location = self.symbol_table.code_location(address);
+        Some(TrapContext {
+            trap,
+            instruction_pointer: address,
+            instruction: self.program.get(address).copied(),
+            location,
+        })
+    }
+
+    pub fn stack(&self) -> &[Float] {
+        &self.stack
+    }
+
+    // active frames, outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn format_frames(&self) -> String {
+        let mut output = String::from("frames:");
+        for (index, frame) in self.frames.iter().enumerate() {
+            // values below the frame may have been popped, taking locals with them.
+            let end = (frame.base + frame.locals).min(self.stack.len());
+            let locals = &self.stack[frame.base.min(end)..end];
+            output.push_str(&format!(
+                "\n    #{} base {}: locals {:?}",
+                index, frame.base, locals
+            ));
+        }
+        output
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halt
+    }
+
+    // the exit code given to `hlt`, once the program halted.
+    pub fn exit_code(&self) -> u8 {
+        self.exit_code
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    // continues execution at `address`, also after the program halted.
+    pub fn set_instruction_pointer(&mut self, address: usize) {
+        self.instruction_pointer = address;
+        self.halt = false;
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // the handler installed for each trap code.
+    pub fn trap_handlers(&self) -> &HashMap<u8, usize> {
+        &self.trap_handlers
+    }
+
+    // replaces the loaded program with one read from bytecode written by `to_bytecode`.
+    pub fn load_bytecode(&mut self, bytes: &[u8]) -> Option<BytecodeError> {
+        let image = match Image::decode(bytes) {
+            Ok(image) => image,
+            Err(err) => return Some(err),
+        };
+        if let Some(max) = self.limits.max_memory_bytes {
+            if image.memory.len() > max {
+                return Some(BytecodeError::LimitExceeded(Limit::Memory));
+            }
+        }
+
+        self.program = image.program;
+        self.memory = image.memory;
+        self.trap_handlers = image.trap_handlers.into_iter().collect();
+        self.symbol_table = SymbolTable::new();
+        for symbol in image.symbols {
+            self.symbol_table.insert(symbol);
+        }
+        self.reset_run();
+        None
+    }
+
+    // replaces the loaded program with one written as numbers: a line per instruction with
+    // its opcode (as in bytecode) and, for instructions that take one, its operand, e.g.
+    // `0 2` for `push 2` and `19` for `add`. `;` starts a comment.
+    pub fn load_numeric(&mut self, source: &str) -> Option<Vec<Diagnostic<LexingError>>> {
+        let mut errors = Vec::new();
+        let mut program = Vec::new();
+        let mut lines = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let code = match line.split_once(';') {
+                Some((code, _)) => code,
+                None => line,
+            };
+            let words: Vec<&str> = code.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
 
-                    if operation.is_empty() {
+            let instruction_type =
+                match words[0].parse().ok().and_then(InstructionType::from_opcode) {
+                    Some(instruction_type) => instruction_type,
+                    None => {
+                        errors.push(Diagnostic {
+                            line: index + 1,
+                            kind: LexingError::IllegalOperation,
+                        });
                         continue;
                     }
+                };
+            // Some(operand) when the operand, or its absence, is right for the instruction.
+            let operand = match (instruction_type.has_operand(), &words[1..]) {
+                (false, []) => Some(None),
+                (true, []) if instruction_type.operand_is_optional() => Some(None),
+                (true, [operand]) => operand.parse::<Float>().ok().map(Some),
+                _ => None,
+            };
+            match operand {
+                Some(operand) => {
+                    program.push(Instruction::new(instruction_type, operand));
+                    lines.push(index + 1);
+                }
+                None => errors.push(Diagnostic {
+                    line: index + 1,
+                    kind: LexingError::IllegalOperand,
+                }),
+            }
+        }
 
-                    match operation {
-                        "pop" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Pop, None));
-                        }
+        for (instruction, line) in program.iter().zip(lines) {
+            if let Some(err) = instruction.validate(program.len()) {
+                errors.push(Diagnostic {
+                    line,
+                    kind: LexingError::InvalidOperand(err),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            errors.sort_by_key(|err| err.line);
+            errors.truncate(self.max_errors);
+            return Some(errors);
+        }
 
-                        "eql" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Equal, None));
-                        }
+        self.program = program;
+        self.memory = Vec::new();
+        self.trap_handlers = HashMap::new();
+        self.symbol_table = SymbolTable::new();
+        self.reset_run();
+        None
+    }
 
-                        "geql" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::GreaterEqual, None));
-                        }
+    // forgets everything the previous program left behind, so a new one starts from the top.
+    fn reset_run(&mut self) {
+        self.stack = Vec::new();
+        self.call_stack = Vec::new();
+        self.try_stack = Vec::new();
+        self.frames = Vec::new();
+        self.frame_pointer = 0;
+        self.instruction_pointer = 0;
+        self.instructions_executed = 0;
+        self.output_bytes = 0;
+        self.halt = false;
+        self.exit_code = 0;
+    }
 
-                        "not" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Not, None));
-                        }
+    pub fn to_bytecode(&self) -> Vec<u8> {
+        let mut trap_handlers: Vec<(u8, usize)> = self
+            .trap_handlers
+            .iter()
+            .map(|(code, handler)| (*code, *handler))
+            .collect();
+        trap_handlers.sort();
+        let mut symbols: Vec<Symbol> = self.symbol_table.iter().cloned().collect();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Image {
+            program: self.program.clone(),
+            memory: self.memory.clone(),
+            trap_handlers,
+            symbols,
+        }
+        .encode()
+    }
 
-                        "add" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Add, None));
-                        }
+    // the loaded program as assembly that assembles back into the same program. global
+    // labels are kept and used for jump, call and handler targets; other operands are
+    // written as plain numbers.
+    pub fn disassemble(&self) -> String {
+        let mut labels: HashMap<(bool, usize), Vec<&Symbol>> = HashMap::new();
+        for symbol in self.symbol_table.labels_by_address() {
+            let is_data = symbol.kind == SymbolKind::DataLabel;
+            labels
+                .entry((is_data, symbol.address().unwrap()))
+                .or_default()
+                .push(symbol);
+        }
+        let code_label = |address: usize| -> Option<String> {
+            labels
+                .get(&(false, address))?
+                .iter()
+                .find(|label| label.visibility != Visibility::Local)
+                .map(|label| label.name.to_string())
+        };
+        let write_labels = |output: &mut String, key: (bool, usize)| {
+            for label in labels.get(&key).into_iter().flatten() {
+                if label.visibility == Visibility::Local {
+                    output.push_str(&format!("; {}\n", label.name));
+                } else {
+                    output.push_str(&format!(".{}:\n", label.name));
+                }
+            }
+        };
 
-                        "sub" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Subtract, None));
-                        }
+        let mut output = String::new();
+        let mut exported: Vec<&Symbol> = self
+            .symbol_table
+            .iter()
+            .filter(|symbol| {
+                symbol.visibility == Visibility::Exported && symbol.kind != SymbolKind::Constant
+            })
+            .collect();
+        exported.sort_by(|a, b| a.name.cmp(&b.name));
+        for symbol in exported {
+            output.push_str(&format!(".export {}\n", symbol.name));
+        }
+        let mut trap_handlers: Vec<(&u8, &usize)> = self.trap_handlers.iter().collect();
+        trap_handlers.sort();
+        for (code, handler) in trap_handlers {
+            if let Some(name) = Trap::name_from_code(*code) {
+                let target = code_label(*handler).unwrap_or_else(|| handler.to_string());
+                output.push_str(&format!(".trap {} {}\n", name, target));
+            }
+        }
 
-                        "mul" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Multiply, None));
-                        }
+        if !self.memory.is_empty() || labels.keys().any(|(is_data, _)| *is_data) {
+            output.push_str(".data\n");
+            let mut address = 0;
+            while address < self.memory.len() {
+                write_labels(&mut output, (true, address));
+                // a line of bytes ends early at the next data label.
+                let mut end = (address + 16).min(self.memory.len());
+                if let Some(next) =
+                    (address + 1..end).find(|next| labels.contains_key(&(true, *next)))
+                {
+                    end = next;
+                }
+                let bytes: Vec<String> = self.memory[address..end]
+                    .iter()
+                    .map(|byte| byte.to_string())
+                    .collect();
+                output.push_str(&format!(".bytes {}\n", bytes.join(", ")));
+                address = end;
+            }
+            write_labels(&mut output, (true, self.memory.len()));
+            output.push_str(".text\n");
+        }
 
-                        "div" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Divide, None));
-                        }
+        for (address, instruction) in self.program.iter().enumerate() {
+            write_labels(&mut output, (false, address));
+            output.push_str(instruction.instruction_type.mnemonic());
+            if let Some(operand) = instruction.operand {
+                let is_target = matches!(
+                    instruction.instruction_type,
+                    InstructionType::Jump
+                        | InstructionType::JumpIf
+                        | InstructionType::Call
+                        | InstructionType::Try
+                );
+                let label = if is_target && operand >= 0. && operand.fract() == 0. {
+                    code_label(operand as usize)
+                } else {
+                    None
+                };
+                output.push(' ');
+                output.push_str(&label.unwrap_or_else(|| format_operand(operand)));
+            }
+            output.push('\n');
+        }
+        write_labels(&mut output, (false, self.program.len()));
+        output
+    }
 
-                        "out" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Output, None));
-                        }
+    // replaces the loaded program with one assembled from `source`.
+    pub fn load_program(&mut self, source: &str) -> Option<Vec<Diagnostic<LexingError>>> {
+        self.program = Vec::new();
+        self.memory = Vec::new();
+        self.trap_handlers = HashMap::new();
+        self.symbol_table = SymbolTable::new();
+        self.warnings = Vec::new();
+        self.reset_run();
+        self.append_program(source)
+    }
 
-                        "outf" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Outputf, None));
-                        }
+    // assembles `source` onto the end of the loaded program, which its labels can refer to.
+    pub fn append_program(&mut self, source: &str) -> Option<Vec<Diagnostic<LexingError>>> {
+        let mut errors = Vec::new();
+        let mut state = AssemblyState {
+            line: 0,
+            in_data_section: false,
+            after_terminator: false,
+            scope: String::new(),
+            referenced_names: HashSet::new(),
+            exports: Vec::new(),
+            numeric_labels: HashMap::new(),
+            fixups: Vec::new(),
+            trap_handlers: Vec::new(),
+            instruction_lines: Vec::new(),
+        };
+        let start = self.program.len();
+        let known_names: HashSet<String> = self
+            .symbol_table
+            .iter()
+            .map(|symbol| symbol.name.to_string())
+            .collect();
+
+        for (index, line) in source.lines().enumerate() {
+            if errors.len() >= self.max_errors {
+                break;
+            }
+            state.line = index + 1;
+
+            let result = match tokenize_line(line) {
+                Ok(tokens) => self.assemble_line(&tokens, &mut state),
+                Err(err) => Some(err),
+            };
+            if let Some(err) = result {
+                errors.push(Diagnostic {
+                    line: state.line,
+                    kind: err,
+                });
+            }
+        }
+
+        let mut unresolved = HashSet::new();
+        for fixup in &state.fixups {
+            let operand = evaluate(&fixup.tokens, &|token| {
+                self.resolve_symbol(token, &fixup.scope, fixup.line, &state.numeric_labels)
+            })
+            .and_then(|value| value.to_operand());
+            match operand {
+                Ok(operand) => self.program[fixup.instruction].operand = Some(operand),
+                Err(err) => {
+                    unresolved.insert(fixup.instruction);
+                    errors.push(Diagnostic {
+                        line: fixup.line,
+                        kind: err,
+                    });
+                }
+            }
+        }
+
+        // jump targets can only be checked once the length of the program is known.
+        for (offset, line) in state.instruction_lines.iter().enumerate() {
+            let address = start + offset;
+            if unresolved.contains(&address) {
+                continue;
+            }
+            if let Some(err) = self.program[address].validate(self.program.len()) {
+                errors.push(Diagnostic {
+                    line: *line,
+                    kind: LexingError::InvalidOperand(err),
+                });
+            }
+        }
+
+        for handler in &state.trap_handlers {
+            let target = evaluate(&handler.tokens, &|token| {
+                self.resolve_symbol(token, &handler.scope, handler.line, &state.numeric_labels)
+            });
+            match target {
+                // handlers are checked like jump targets.
+                Ok(Value::Integer(address @ 0..)) if address as usize >= self.program.len() => {
+                    errors.push(Diagnostic {
+                        line: handler.line,
+                        kind: LexingError::InvalidOperand(OperandError::TargetOutOfBounds(
+                            address as usize,
+                        )),
+                    })
+                }
+                Ok(Value::Integer(address @ 0..)) => {
+                    self.trap_handlers.insert(handler.code, address as usize);
+                }
+                Ok(_) => errors.push(Diagnostic {
+                    line: handler.line,
+                    kind: LexingError::IllegalOperand,
+                }),
+                Err(err) => errors.push(Diagnostic {
+                    line: handler.line,
+                    kind: err,
+                }),
+            }
+        }
+
+        for (name, line) in &state.exports {
+            if !self.symbol_table.export(name) {
+                errors.push(Diagnostic {
+                    line: *line,
+                    kind: LexingError::UndefinedSymbol(name.to_string()),
+                });
+            }
+        }
+        errors.sort_by_key(|err| err.line);
+        errors.truncate(self.max_errors);
+
+        // labels from earlier loads were already warned about.
+        for symbol in self.symbol_table.iter() {
+            if known_names.contains(symbol.name.as_str()) {
+                continue;
+            }
+            let is_used = state.referenced_names.contains(&symbol.name)
+                || symbol.visibility == Visibility::Exported;
+            if symbol.address().is_some() && !is_used {
+                self.warnings.push(Diagnostic {
+                    line: symbol.line,
+                    kind: LexingWarning::UnusedLabel(symbol.name.to_string()),
+                });
+            }
+        }
+        self.warnings.sort_by_key(|warning| warning.line);
+
+        if errors.is_empty() {
+            None
+        } else {
+            Some(errors)
+        }
+    }
+
+    pub fn warnings(&self) -> &[Diagnostic<LexingWarning>] {
+        &self.warnings
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbol_table
+    }
+
+    // makes `function` callable as `native <name>` (or `callnative <id>`) and returns its id.
+    // natives have to be registered before the program using them is loaded.
+    pub fn register_native<F>(
+        &mut self,
+        name: &str,
+        arity: usize,
+        returns: usize,
+        function: F,
+    ) -> usize
+    where
+        F: FnMut(&[Float]) -> Result<Vec<Float>, String> + 'static,
+    {
+        self.natives.register(Native {
+            name: name.to_string(),
+            arity,
+            returns,
+            function: Box::new(function),
+        })
+    }
+
+    pub fn natives(&self) -> &NativeRegistry {
+        &self.natives
+    }
+
+    // mnemonics and directives are matched case-insensitively when enabled; symbol names never are.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+    }
+
+    pub fn set_max_errors(&mut self, max_errors: usize) {
+        self.max_errors = max_errors;
+    }
+
+    pub fn set_limits(&mut self, limits: VmLimits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &VmLimits {
+        &self.limits
+    }
+
+    // how arithmetic is done, with floats by default.
+    pub fn set_numeric(&mut self, numeric: Numeric) {
+        self.numeric = numeric;
+    }
+
+    pub fn numeric(&self) -> Numeric {
+        self.numeric
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
 
-                        "dmp" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Dump, None));
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions_executed
+    }
+
+    pub fn output_bytes(&self) -> usize {
+        self.output_bytes
+    }
+
+    // sends the program's output to `output` instead, returning the previous destination.
+    pub fn set_output(&mut self, output: Box<dyn Write>) -> Box<dyn Write> {
+        std::mem::replace(&mut self.output, output)
+    }
+
+    fn assemble_line(
+        &mut self,
+        tokens: &[Token],
+        state: &mut AssemblyState,
+    ) -> Option<LexingError> {
+        match tokens {
+            [] => None,
+
+            [Token::Directive(label_name), Token::Colon] => {
+                state.scope = label_name.to_string();
+                self.define_label(label_name, Visibility::Global, state)
+            }
+
+            [Token::LocalName(label_name), Token::Colon] => {
+                let label_name = format!("{}@{}", state.scope, label_name);
+                self.define_label(&label_name, Visibility::Local, state)
+            }
+
+            [Token::Number(Value::Integer(number @ 0..)), Token::Colon] => {
+                let position = self.label_position(state);
+                state
+                    .numeric_labels
+                    .entry(*number)
+                    .or_default()
+                    .push((state.line, position));
+                None
+            }
+
+            [Token::Directive(directive), operand @ ..] => {
+                let directive = self.normalize_case(directive);
+                record_references(operand, state);
+                match (directive.as_str(), operand) {
+                    ("data", []) => {
+                        state.in_data_section = true;
+                        None
+                    }
+
+                    ("text", []) => {
+                        state.in_data_section = false;
+                        None
+                    }
+
+                    ("const", [Token::Name(name), expression @ ..]) => {
+                        self.define_constant(name, expression, state)
+                    }
+
+                    ("const", _) => Some(LexingError::IllegalConstant),
+
+                    ("export", [Token::Name(name)]) => {
+                        state.exports.push((name.to_string(), state.line));
+                        None
+                    }
+
+                    ("export", [Token::LocalName(name)]) => {
+                        let name = format!("{}@{}", state.scope, name);
+                        state.exports.push((name, state.line));
+                        None
+                    }
+
+                    ("export", _) => Some(LexingError::IllegalOperand),
+
+                    ("trap", [Token::Name(trap_name), target @ ..]) => {
+                        match Trap::code_from_name(trap_name) {
+                            Some(code) if !target.is_empty() => {
+                                state.trap_handlers.push(PendingTrapHandler {
+                                    code,
+                                    tokens: target.to_vec(),
+                                    scope: state.scope.clone(),
+                                    line: state.line,
+                                });
+                                None
+                            }
+                            Some(_) => Some(LexingError::IllegalOperand),
+                            None => Some(LexingError::UndefinedTrap(trap_name.to_string())),
                         }
+                    }
 
-                        "hlt" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Halt, None));
+                    ("trap", _) => Some(LexingError::IllegalOperand),
+
+                    ("string" | "bytes" | "floats", _) => {
+                        if !state.in_data_section {
+                            return Some(LexingError::IllegalSection);
                         }
+                        self.define_data(&directive, operand, state)
+                    }
+
+                    _ => Some(LexingError::IllegalDirective),
+                }
+            }
 
-                        _ => {
-                            if operation.starts_with(".") && operation.ends_with(":") {
-                                let label_name = instruction[0]
-                                    .strip_prefix(".")
-                                    .unwrap()
-                                    .strip_suffix(":")
-                                    .unwrap()
-                                    .to_string();
-                                self.label_table
-                                    .push(Label::new(label_name, instruction_index));
-                            } else {
-                                return Some(LexingError::IllegalLabel);
+            [Token::Name(mnemonic), operand @ ..] => {
+                if state.in_data_section {
+                    return Some(LexingError::IllegalSection);
+                }
+                record_references(operand, state);
+
+                // `native <name>` is `callnative` with the id the name was registered under.
+                let mnemonic = self.normalize_case(mnemonic);
+                let native_id;
+                let (mnemonic, operand) = match (mnemonic.as_str(), operand) {
+                    ("native", [Token::Name(name)]) => match self.natives.find(name) {
+                        Some(id) => {
+                            native_id = [Token::Number(Value::Integer(id as Integer))];
+                            ("callnative", &native_id[..])
+                        }
+                        None => return Some(LexingError::UndefinedNative(name.to_string())),
+                    },
+                    ("native", _) => return Some(LexingError::IllegalOperand),
+                    (mnemonic, operand) => (mnemonic, operand),
+                };
+
+                let instruction_type = match InstructionType::from_mnemonic(mnemonic) {
+                    Some(instruction_type) => instruction_type,
+                    None => return Some(LexingError::IllegalOperation),
+                };
+
+                let operand = match (instruction_type.has_operand(), operand) {
+                    (false, []) => None,
+                    (true, []) if instruction_type.operand_is_optional() => None,
+                    (true, [_, ..]) => {
+                        let value = evaluate(operand, &|token| {
+                            self.resolve_symbol(
+                                token,
+                                &state.scope,
+                                state.line,
+                                &state.numeric_labels,
+                            )
+                        });
+                        match value.and_then(|value| value.to_operand()) {
+                            Ok(operand) => Some(operand),
+                            Err(LexingError::UndefinedSymbol(_)) => {
+                                // the symbol may be defined further down, so try again at the end.
+                                state.fixups.push(Fixup {
+                                    instruction: self.program.len(),
+                                    tokens: operand.to_vec(),
+                                    scope: state.scope.clone(),
+                                    line: state.line,
+                                });
+                                Some(0.)
                             }
+                            Err(err) => return Some(err),
                         }
                     }
+                    _ => return Some(LexingError::IllegalOperand),
+                };
+
+                if state.after_terminator {
+                    self.warnings.push(Diagnostic {
+                        line: state.line,
+                        kind: LexingWarning::UnreachableCode,
+                    });
+                    // one warning per unreachable block is enough.
+                    state.after_terminator = false;
+                } else if matches!(
+                    instruction_type,
+                    InstructionType::Halt
+                        | InstructionType::Jump
+                        | InstructionType::Return
+                        | InstructionType::Throw
+                ) {
+                    state.after_terminator = true;
                 }
 
-                2 => {
-                    let operation = instruction[0].trim();
-                    let operand = instruction[1].trim();
+                state.instruction_lines.push(state.line);
+                self.program
+                    .push(Instruction::new(instruction_type, operand));
+                None
+            }
 
-                    let operand: Float = match operand.parse() {
-                        Ok(operand) => operand,
-                        Err(_) => {
-                            if let Some(operand) = self.label_table.find(operand) {
-                                operand as Float
-                            } else {
-                                return Some(LexingError::IllegalOperand);
-                            }
-                        }
-                    };
+            _ => Some(LexingError::IllegalOperation),
+        }
+    }
 
-                    match operation {
-                        "push" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Push, Some(operand)));
-                        }
+    fn normalize_case(&self, name: &str) -> String {
+        if self.case_insensitive {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
 
-                        "dup" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Duplicate, Some(operand)));
-                        }
+    fn label_position(&self, state: &mut AssemblyState) -> usize {
+        // labels in the data section point at the memory address of the data.
+        if state.in_data_section {
+            self.memory.len()
+        } else {
+            // a label makes the code after it reachable again through jumps.
+            state.after_terminator = false;
+            self.program.len()
+        }
+    }
 
-                        "swp" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Swap, Some(operand)));
-                        }
+    fn define_label(
+        &mut self,
+        label_name: &str,
+        visibility: Visibility,
+        state: &mut AssemblyState,
+    ) -> Option<LexingError> {
+        if self.symbol_table.contains(label_name) {
+            return Some(LexingError::DuplicateSymbol(label_name.to_string()));
+        }
+        let kind = if state.in_data_section {
+            SymbolKind::DataLabel
+        } else {
+            SymbolKind::CodeLabel
+        };
+        let position = self.label_position(state);
+        self.symbol_table.insert(Symbol::new(
+            label_name.to_string(),
+            kind,
+            Value::Integer(position as Integer),
+            state.line,
+            visibility,
+        ));
+        None
+    }
 
-                        "jmp" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::Jump, Some(operand)));
-                        }
+    // numeric labels refer to the closest definition after (`1f`) or before (`1b`) the line
+    // they are used on; everything else is looked up in the symbol table.
+    fn resolve_symbol(
+        &self,
+        token: &Token,
+        scope: &str,
+        line: usize,
+        numeric_labels: &HashMap<Integer, Vec<(usize, usize)>>,
+    ) -> Result<Value, LexingError> {
+        let name = match token {
+            Token::Name(name) => name.to_string(),
+            Token::LocalName(name) => format!("{}@{}", scope, name),
+            Token::NumericLabel { number, forward } => {
+                let definitions = numeric_labels.get(number).map(Vec::as_slice).unwrap_or(&[]);
+                let definition = if *forward {
+                    definitions
+                        .iter()
+                        .find(|(defined_at, _)| *defined_at > line)
+                } else {
+                    definitions
+                        .iter()
+                        .rev()
+                        .find(|(defined_at, _)| *defined_at < line)
+                };
+                return match definition {
+                    Some((_, position)) => Ok(Value::Integer(*position as Integer)),
+                    None => Err(LexingError::UndefinedSymbol(format!(
+                        "{}{}",
+                        number,
+                        if *forward { "f" } else { "b" }
+                    ))),
+                };
+            }
+            _ => return Err(LexingError::InvalidExpression),
+        };
 
-                        "jmpif" => {
-                            instruction_index += 1;
-                            self.program
-                                .push(Instruction::new(InstructionType::JumpIf, Some(operand)));
-                        }
+        match self.symbol_table.get(&name) {
+            Some(symbol) => Ok(symbol.value),
+            None => Err(LexingError::UndefinedSymbol(name)),
+        }
+    }
 
-                        _ => {
-                            return Some(LexingError::IllegalOperation);
-                        }
+    fn define_constant(
+        &mut self,
+        name: &str,
+        expression: &[Token],
+        state: &AssemblyState,
+    ) -> Option<LexingError> {
+        if is_reserved_name(name) {
+            return Some(LexingError::IllegalConstant);
+        }
+        if self.symbol_table.contains(name) {
+            return Some(LexingError::DuplicateSymbol(name.to_string()));
+        }
+
+        let value = evaluate(expression, &|token| {
+            self.resolve_symbol(token, &state.scope, state.line, &state.numeric_labels)
+        });
+        match value {
+            Ok(value) => {
+                self.symbol_table.insert(Symbol::new(
+                    name.to_string(),
+                    SymbolKind::Constant,
+                    value,
+                    state.line,
+                    Visibility::Global,
+                ));
+                None
+            }
+            Err(err) => Some(err),
+        }
+    }
+
+    fn define_data(
+        &mut self,
+        directive: &str,
+        operand: &[Token],
+        state: &AssemblyState,
+    ) -> Option<LexingError> {
+        let resolve = |token: &Token| {
+            self.resolve_symbol(token, &state.scope, state.line, &state.numeric_labels)
+        };
+        let values = match directive {
+            "string" => Vec::new(),
+            _ => match evaluate_list(operand, &resolve) {
+                Ok(values) => values,
+                Err(err) => return Some(err),
+            },
+        };
+
+        match directive {
+            "string" => match operand {
+                [Token::String(string)] => {
+                    // strings are stored nul-terminated, which is where `outs` stops printing.
+                    self.memory.extend_from_slice(string.as_bytes());
+                    self.memory.push(0);
+                }
+                _ => return Some(LexingError::IllegalOperand),
+            },
+
+            "bytes" => {
+                for value in values {
+                    match value {
+                        Value::Integer(byte @ 0..=255) => self.memory.push(byte as u8),
+                        Value::Integer(_) => return Some(LexingError::ExpressionOverflow),
+                        Value::Float(_) => return Some(LexingError::NonIntegerOperand),
                     }
                 }
+            }
+
+            _ => {
+                for value in values {
+                    self.memory
+                        .extend_from_slice(&value.as_float().to_le_bytes());
+                }
+            }
+        }
 
-                _ => return Some(LexingError::IllegalOperand),
+        match self.limits.max_memory_bytes {
+            Some(max) if self.memory.len() > max => Some(LexingError::LimitExceeded(Limit::Memory)),
+            _ => None,
+        }
+    }
+
+    fn read_string(&self, address: Float) -> Option<String> {
+        if address < 0. || address.fract() != 0. || address >= self.memory.len() as Float {
+            return None;
+        }
+        let bytes = &self.memory[address as usize..];
+        let length = bytes.iter().position(|byte| *byte == 0)?;
+        Some(String::from_utf8_lossy(&bytes[..length]).into_owned())
+    }
+
+    // checked before every instruction, so running into a limit leaves the vm untouched.
+    fn check_limits(&self) -> Option<Trap> {
+        if let Some(max) = self.limits.max_instructions {
+            if self.instructions_executed >= max {
+                return Some(Trap::LimitExceeded(Limit::Instructions));
+            }
+        }
+        let instruction = self.program.get(self.instruction_pointer)?;
+        if let Some(max) = self.limits.max_call_depth {
+            if instruction.instruction_type == InstructionType::Call && self.call_stack.len() >= max
+            {
+                return Some(Trap::LimitExceeded(Limit::CallDepth));
+            }
+        }
+        if !self.fits_on_stack(self.stack_growth(instruction)) {
+            return Some(Trap::StackOverflow);
+        }
+        None
+    }
+
+    fn fits_on_stack(&self, values: usize) -> bool {
+        match self.limits.max_stack_depth {
+            Some(max) => self.stack_usage().saturating_add(values) <= max,
+            None => true,
+        }
+    }
+
+    // what counts towards the stack limit: the values, and one for every open frame and
+    // pending `try` handler, so those can not pile up either.
+    fn stack_usage(&self) -> usize {
+        self.stack.len() + self.frames.len() + self.try_stack.len()
+    }
+
+    // the most an instruction can grow the stack (as counted by `stack_usage`) by.
+    fn stack_growth(&self, instruction: &Instruction) -> usize {
+        match instruction.instruction_type {
+            InstructionType::Push
+            | InstructionType::Duplicate
+            | InstructionType::LoadLocal
+            | InstructionType::LoadArgument
+            | InstructionType::Throw => 1,
+            // the frame and the handler count too, but not when the operand already traps.
+            InstructionType::Try => index_operand(instruction.operand).map_or(0, |_| 1),
+            InstructionType::Enter => {
+                index_operand(instruction.operand).map_or(0, |locals| locals.saturating_add(1))
+            }
+            InstructionType::CallNative => index_operand(instruction.operand)
+                .and_then(|id| self.natives.get(id))
+                .map_or(0, |native| native.returns.saturating_sub(native.arity)),
+            _ => 0,
+        }
+    }
+
+    // replaces the top two values with the result of the operation on them.
+    fn arithmetic(&mut self, operation: Operation) -> Option<Trap> {
+        let b = self.stack[self.stack.len() - 1];
+        let a = self.stack[self.stack.len() - 2];
+        match self.numeric.apply(operation, a, b) {
+            Ok(result) => {
+                self.stack.truncate(self.stack.len() - 2);
+                self.stack.push(result);
+                None
             }
+            Err(trap) => Some(trap),
         }
+    }
+
+    fn write_output(&mut self, text: &str) -> Option<Trap> {
+        if let Some(max) = self.limits.max_output_bytes {
+            if self.output_bytes.saturating_add(text.len()) > max {
+                return Some(Trap::LimitExceeded(Limit::Output));
+            }
+        }
+        self.output_bytes += text.len();
+        self.output.write_all(text.as_bytes()).ok();
+        self.output.flush().ok();
         None
     }
 
-    fn execute_instruction(&mut self) -> Option<ParsingError> {
+    fn execute_instruction(&mut self) -> Option<Trap> {
         if self.instruction_pointer >= self.program.len() {
-            return Some(ParsingError::InvalidInstructionPointer);
+            return Some(Trap::InvalidInstructionPointer);
         }
-        let instruction = &self.program[self.instruction_pointer];
+        let instruction = self.program[self.instruction_pointer];
 
         match instruction.instruction_type {
             InstructionType::Push => {
                 self.instruction_pointer += 1;
 
-                if let Some(operand) = instruction.operand {
-                    self.stack.push(operand);
-                } else {
-                    return Some(ParsingError::IllegalOperand);
+                let operand = match instruction.operand {
+                    Some(operand) => operand,
+                    None => return Some(Trap::IllegalOperand),
+                };
+                match self.numeric.value(operand) {
+                    Ok(value) => self.stack.push(value),
+                    Err(trap) => return Some(trap),
                 }
             }
 
             InstructionType::Pop => {
                 self.instruction_pointer += 1;
 
-                if self.stack.len() < 1 {
-                    return Some(ParsingError::StackUnderflow);
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
                 }
 
                 self.stack.pop();
@@ -257,99 +1431,90 @@ impl UVM {
             InstructionType::Duplicate => {
                 self.instruction_pointer += 1;
 
-                if let Some(instruction_pointer) = instruction.operand {
-                    let stack_length = self.stack.len() as Float;
-                    if stack_length - instruction_pointer < 1. {
-                        return Some(ParsingError::StackUnderflow);
-                    }
-                    if instruction_pointer < 0. {
-                        return Some(ParsingError::IllegalOperand);
-                    } else {
-                        // it's performing a relative jump; jumping <operand> up.
-                        self.stack
-                            .push(self.stack[(stack_length - 1. - instruction_pointer) as usize]);
-                    }
+                // `dup n` copies the value n places below the top.
+                let offset = match index_operand(instruction.operand) {
+                    Some(offset) => offset,
+                    None => return Some(Trap::IllegalOperand),
+                };
+                if offset >= self.stack.len() {
+                    return Some(Trap::StackUnderflow);
                 }
+                self.stack.push(self.stack[self.stack.len() - 1 - offset]);
             }
 
             InstructionType::Swap => {
                 self.instruction_pointer += 1;
 
-                if let Some(instruction_pointer) = instruction.operand {
-                    let stack_length = self.stack.len() as Float;
-                    if stack_length - instruction_pointer < 1. {
-                        return Some(ParsingError::StackUnderflow);
-                    }
-                    if instruction_pointer <= 0. {
-                        return Some(ParsingError::IllegalOperand);
-                    } else {
-                        // it's performing a relative swap; swaping <operand> and pop.
-                        let a = self.stack[(stack_length - 1. - instruction_pointer) as usize];
-                        let b = self.stack.pop().unwrap();
-                        self.stack[(stack_length - 1. - instruction_pointer) as usize] = b;
-                        self.stack.push(a);
-                    }
+                // `swp n` swaps the top with the value n places below it.
+                let offset = match index_operand(instruction.operand) {
+                    Some(offset) if offset > 0 => offset,
+                    _ => return Some(Trap::IllegalOperand),
+                };
+                if offset >= self.stack.len() {
+                    return Some(Trap::StackUnderflow);
                 }
+                let top = self.stack.len() - 1;
+                self.stack.swap(top, top - offset);
             }
 
             InstructionType::Add => {
                 self.instruction_pointer += 1;
 
                 if self.stack.len() < 2 {
-                    return Some(ParsingError::StackUnderflow);
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let b = self.stack.pop().unwrap();
-                let a = self.stack.pop().unwrap();
-                self.stack.push(a + b);
+                if let Some(trap) = self.arithmetic(Operation::Add) {
+                    return Some(trap);
+                }
             }
 
             InstructionType::Subtract => {
                 self.instruction_pointer += 1;
 
                 if self.stack.len() < 2 {
-                    return Some(ParsingError::StackUnderflow);
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let b = self.stack.pop().unwrap();
-                let a = self.stack.pop().unwrap();
-                self.stack.push(a - b);
+                if let Some(trap) = self.arithmetic(Operation::Subtract) {
+                    return Some(trap);
+                }
             }
 
             InstructionType::Multiply => {
                 self.instruction_pointer += 1;
 
                 if self.stack.len() < 2 {
-                    return Some(ParsingError::StackUnderflow);
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let b = self.stack.pop().unwrap();
-                let a = self.stack.pop().unwrap();
-                self.stack.push(a * b);
+                if let Some(trap) = self.arithmetic(Operation::Multiply) {
+                    return Some(trap);
+                }
             }
 
             InstructionType::Divide => {
                 self.instruction_pointer += 1;
 
                 if self.stack.len() < 2 {
-                    return Some(ParsingError::StackUnderflow);
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let b = self.stack.pop().unwrap();
-                let a = self.stack.pop().unwrap();
-
-                if b == 0. {
-                    return Some(ParsingError::DivisionByZero);
+                // check before popping, so the operands are still there for a trap handler.
+                if self.stack[self.stack.len() - 1] == 0. {
+                    return Some(Trap::DivisionByZero);
                 }
 
-                self.stack.push(a / b);
+                if let Some(trap) = self.arithmetic(Operation::Divide) {
+                    return Some(trap);
+                }
             }
 
             InstructionType::Equal => {
                 self.instruction_pointer += 1;
 
                 if self.stack.len() < 2 {
-                    return Some(ParsingError::StackUnderflow);
+                    return Some(Trap::StackUnderflow);
                 }
 
                 let b = self.stack.pop().unwrap();
@@ -361,7 +1526,7 @@ impl UVM {
                 self.instruction_pointer += 1;
 
                 if self.stack.len() < 2 {
-                    return Some(ParsingError::StackUnderflow);
+                    return Some(Trap::StackUnderflow);
                 }
 
                 let b = self.stack.pop().unwrap();
@@ -372,8 +1537,8 @@ impl UVM {
             InstructionType::Not => {
                 self.instruction_pointer += 1;
 
-                if self.stack.len() < 1 {
-                    return Some(ParsingError::StackUnderflow);
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
                 }
 
                 let a = self.stack.pop().unwrap();
@@ -382,65 +1547,345 @@ impl UVM {
             }
 
             InstructionType::Jump => {
-                if let Some(jump_to) = instruction.operand {
-                    self.instruction_pointer = jump_to as usize;
+                if let Some(jump_to) = index_operand(instruction.operand) {
+                    self.instruction_pointer = jump_to;
                 } else {
-                    return Some(ParsingError::IllegalOperand);
+                    return Some(Trap::IllegalOperand);
                 }
             }
 
             InstructionType::JumpIf => {
                 self.instruction_pointer += 1;
 
-                if self.stack.len() < 1 {
-                    return Some(ParsingError::StackUnderflow);
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let a = self.stack.pop().unwrap();
-                self.stack.push(a);
-                if let Some(jump_to) = instruction.operand {
+                let a = self.stack[self.stack.len() - 1];
+                if let Some(jump_to) = index_operand(instruction.operand) {
                     if a != 0. {
-                        self.instruction_pointer = jump_to as usize;
+                        self.instruction_pointer = jump_to;
+                    }
+                } else {
+                    return Some(Trap::IllegalOperand);
+                }
+            }
+
+            InstructionType::Call => {
+                if let Some(call_to) = index_operand(instruction.operand) {
+                    self.call_stack.push(self.instruction_pointer + 1);
+                    self.instruction_pointer = call_to;
+                } else {
+                    return Some(Trap::IllegalOperand);
+                }
+            }
+
+            InstructionType::Return => {
+                if let Some(return_to) = self.call_stack.pop() {
+                    self.instruction_pointer = return_to;
+                } else {
+                    return Some(Trap::CallStackUnderflow);
+                }
+
+                // try blocks and frames left open by the returning call are closed with it.
+                while let Some(frame) = self.try_stack.last() {
+                    if frame.call_depth <= self.call_stack.len() {
+                        break;
+                    }
+                    self.try_stack.pop();
+                }
+                while let Some(frame) = self.frames.last() {
+                    if frame.call_depth <= self.call_stack.len() {
+                        break;
+                    }
+                    self.frame_pointer = frame.saved_frame_pointer;
+                    self.frames.pop();
+                }
+            }
+
+            InstructionType::Enter => {
+                self.instruction_pointer += 1;
+
+                let locals = match index_operand(instruction.operand) {
+                    Some(locals) => locals,
+                    None => return Some(Trap::IllegalOperand),
+                };
+                // even without a stack limit, locals that can not be allocated are an overflow.
+                if self.stack.try_reserve(locals).is_err() {
+                    return Some(Trap::StackOverflow);
+                }
+                self.frames.push(Frame {
+                    base: self.stack.len(),
+                    locals,
+                    saved_frame_pointer: self.frame_pointer,
+                    call_depth: self.call_stack.len(),
+                });
+                self.frame_pointer = self.stack.len();
+                self.stack.resize(self.stack.len() + locals, 0.);
+            }
+
+            InstructionType::Leave => {
+                self.instruction_pointer += 1;
+
+                let frame = match self.frames.pop() {
+                    Some(frame) => frame,
+                    None => return Some(Trap::InvalidFrame),
+                };
+                // drop the locals but keep whatever was pushed above them, e.g. return values.
+                let locals_end = (frame.base + frame.locals).min(self.stack.len());
+                self.stack.drain(frame.base.min(locals_end)..locals_end);
+                self.frame_pointer = frame.saved_frame_pointer;
+            }
+
+            InstructionType::LoadLocal | InstructionType::StoreLocal => {
+                self.instruction_pointer += 1;
+
+                let locals = match self.frames.last() {
+                    Some(frame) => frame.locals,
+                    None => return Some(Trap::InvalidFrame),
+                };
+                let index = match index_operand(instruction.operand) {
+                    Some(index) if index < locals => self.frame_pointer + index,
+                    _ => return Some(Trap::IllegalOperand),
+                };
+                if index >= self.stack.len() {
+                    return Some(Trap::StackUnderflow);
+                }
+
+                if instruction.instruction_type == InstructionType::LoadLocal {
+                    self.stack.push(self.stack[index]);
+                } else {
+                    // never pop a local itself to store it.
+                    if self.stack.len() <= self.frame_pointer + locals {
+                        return Some(Trap::StackUnderflow);
+                    }
+                    let value = self.stack.pop().unwrap();
+                    self.stack[index] = value;
+                }
+            }
+
+            InstructionType::LoadArgument => {
+                self.instruction_pointer += 1;
+
+                if self.frames.is_empty() {
+                    return Some(Trap::InvalidFrame);
+                }
+                // `aload 0` is the argument pushed last, right below the frame.
+                match index_operand(instruction.operand) {
+                    Some(index) => {
+                        // the arguments may have been popped since the frame was entered.
+                        if index >= self.frame_pointer
+                            || self.frame_pointer - 1 - index >= self.stack.len()
+                        {
+                            return Some(Trap::StackUnderflow);
+                        }
+                        self.stack.push(self.stack[self.frame_pointer - 1 - index]);
                     }
+                    None => return Some(Trap::IllegalOperand),
+                }
+            }
+
+            InstructionType::Try => {
+                if let Some(handler) = index_operand(instruction.operand) {
+                    self.instruction_pointer += 1;
+                    self.try_stack.push(TryFrame {
+                        handler,
+                        stack_depth: self.stack.len(),
+                        call_depth: self.call_stack.len(),
+                        frame_depth: self.frames.len(),
+                    });
                 } else {
-                    return Some(ParsingError::IllegalOperand);
+                    return Some(Trap::IllegalOperand);
                 }
             }
 
+            InstructionType::EndTry => {
+                if self.try_stack.pop().is_none() {
+                    return Some(Trap::IllegalOperation);
+                }
+                self.instruction_pointer += 1;
+            }
+
+            InstructionType::Throw => {
+                let value = match self.stack.last() {
+                    Some(value) => *value,
+                    None => return Some(Trap::StackUnderflow),
+                };
+                let frame = match self.try_stack.pop() {
+                    Some(frame) => frame,
+                    None => return Some(Trap::UncaughtThrow(value)),
+                };
+
+                // unwind to the state at `try`, then hand the thrown value to the handler.
+                self.stack.truncate(frame.stack_depth);
+                self.call_stack.truncate(frame.call_depth);
+                while self.frames.len() > frame.frame_depth {
+                    self.frame_pointer = self.frames.pop().unwrap().saved_frame_pointer;
+                }
+                self.stack.push(value);
+                self.instruction_pointer = frame.handler;
+            }
+
             InstructionType::Output => {
                 self.instruction_pointer += 1;
 
-                if self.stack.len() < 1 {
-                    return Some(ParsingError::StackUnderflow);
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let a = self.stack.pop().unwrap();
-                println!("{}", a);
-                self.stack.push(a);
+                let a = self.stack[self.stack.len() - 1];
+                if let Some(trap) = self.write_output(&format!("{}\n", a)) {
+                    return Some(trap);
+                }
             }
 
             InstructionType::Outputf => {
                 self.instruction_pointer += 1;
 
-                if self.stack.len() < 1 {
-                    return Some(ParsingError::StackUnderflow);
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
                 }
 
-                let a = self.stack.pop().unwrap();
-                println!("{:.15}", a);
-                self.stack.push(a);
+                let a = self.stack[self.stack.len() - 1];
+                if let Some(trap) = self.write_output(&format!("{:.15}\n", a)) {
+                    return Some(trap);
+                }
+            }
+
+            InstructionType::OutputString => {
+                self.instruction_pointer += 1;
+
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
+                }
+
+                let address = self.stack[self.stack.len() - 1];
+                if let Some(string) = self.read_string(address) {
+                    if let Some(trap) = self.write_output(&string) {
+                        return Some(trap);
+                    }
+                } else {
+                    return Some(Trap::InvalidMemoryAddress);
+                }
+            }
+
+            InstructionType::OutputCharacter => {
+                self.instruction_pointer += 1;
+
+                if self.stack.is_empty() {
+                    return Some(Trap::StackUnderflow);
+                }
+
+                let code = self.stack[self.stack.len() - 1];
+                if code < 0. || code.fract() != 0. || code > u32::MAX as Float {
+                    return Some(Trap::IllegalOperand);
+                }
+                if let Some(character) = char::from_u32(code as u32) {
+                    if let Some(trap) = self.write_output(&character.to_string()) {
+                        return Some(trap);
+                    }
+                } else {
+                    return Some(Trap::IllegalOperand);
+                }
+            }
+
+            InstructionType::CallNative => {
+                self.instruction_pointer += 1;
+
+                let id = match index_operand(instruction.operand) {
+                    Some(id) => id,
+                    None => return Some(Trap::IllegalOperand),
+                };
+                let native = match self.natives.get_mut(id) {
+                    Some(native) => native,
+                    None => return Some(Trap::IllegalOperand),
+                };
+
+                if self.stack.len() < native.arity {
+                    return Some(Trap::StackUnderflow);
+                }
+
+                let arguments = self.stack.split_off(self.stack.len() - native.arity);
+                match (native.function)(&arguments) {
+                    Ok(results) => {
+                        if results.len() != native.returns {
+                            return Some(Trap::NativeError(format!(
+                                "{} returned {} values, expected {}",
+                                native.name,
+                                results.len(),
+                                native.returns
+                            )));
+                        }
+                        self.stack.extend(results);
+                    }
+                    Err(message) => {
+                        return Some(Trap::NativeError(format!("{}: {}", native.name, message)));
+                    }
+                }
             }
 
             InstructionType::Dump => {
                 self.instruction_pointer += 1;
 
-                println!("stack: {:#?}", self.stack);
+                let mut dump = format!("stack: {:#?}\n", self.stack);
+                if !self.frames.is_empty() {
+                    dump.push_str(&format!("{}\n", self.format_frames()));
+                }
+                if let Some(trap) = self.write_output(&dump) {
+                    return Some(trap);
+                }
             }
 
             InstructionType::Halt => {
+                // `hlt` on its own exits with 0.
+                let exit_code = match instruction.operand {
+                    None => 0,
+                    Some(code) if (0. ..=255.).contains(&code) && code.fract() == 0. => code as u8,
+                    Some(_) => return Some(Trap::IllegalOperand),
+                };
+                self.exit_code = exit_code;
                 self.halt = true;
             }
         }
         None
     }
 }
+
+// an operand used as a stack offset, a count or an instruction index. a fraction, a negative
+// number, nan or infinity is never one.
+pub(crate) fn index_operand(operand: Option<Float>) -> Option<usize> {
+    match operand {
+        Some(value) if value >= 0. && value.fract() == 0. && value < usize::MAX as Float => {
+            Some(value as usize)
+        }
+        _ => None,
+    }
+}
+
+// an operand written so the lexer reads back exactly the same float.
+fn format_operand(operand: Float) -> String {
+    let is_integer = operand.fract() == 0. && operand.abs() < (1u64 << 53) as Float;
+    if is_integer && !(operand == 0. && operand.is_sign_negative()) {
+        format!("{}", operand)
+    } else if operand.is_nan() && operand.is_sign_negative() {
+        String::from("-nan")
+    } else {
+        format!("{:?}", operand)
+    }
+}
+
+fn record_references(tokens: &[Token], state: &mut AssemblyState) {
+    for token in tokens {
+        match token {
+            Token::Name(name) => {
+                state.referenced_names.insert(name.to_string());
+            }
+            Token::LocalName(name) => {
+                state
+                    .referenced_names
+                    .insert(format!("{}@{}", state.scope, name));
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,14 +1,157 @@
+use std::fmt;
+
+use crate::{global::Float, limits::Limit};
+
 #[derive(Debug)]
 pub enum LexingError {
     IllegalOperation,
     IllegalOperand,
     IllegalLabel,
+    IllegalConstant,
+    IllegalSection,
+    IllegalDirective,
+    IllegalCharacter(char),
+    DuplicateSymbol(String),
+    UndefinedSymbol(String),
+    UndefinedNative(String),
+    UndefinedTrap(String),
+    InvalidExpression,
+    IllegalLiteral,
+    NonIntegerOperand,
+    ExpressionOverflow,
+    ExpressionDivisionByZero,
+    InvalidOperand(OperandError),
+    // the data section does not fit in `max_memory_bytes`.
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for LexingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexingError::DuplicateSymbol(name) => write!(f, "DuplicateSymbol `{}`", name),
+            LexingError::UndefinedSymbol(name) => write!(f, "UndefinedSymbol `{}`", name),
+            LexingError::UndefinedNative(name) => write!(f, "UndefinedNative `{}`", name),
+            LexingError::UndefinedTrap(name) => write!(f, "UndefinedTrap `{}`", name),
+            LexingError::IllegalCharacter(character) => {
+                write!(f, "IllegalCharacter `{}`", character.escape_default())
+            }
+            LexingError::InvalidOperand(err) => write!(f, "{}", err),
+            LexingError::LimitExceeded(limit) => write!(f, "LimitExceeded: {}", limit),
+            _ => write!(f, "{:?}", self),
+        }
+    }
 }
 
 #[derive(Debug)]
-pub enum ParsingError {
-    StackUnderflow,
-    DivisionByZero,
-    InvalidInstructionPointer,
-    IllegalOperand,
+pub enum LexingWarning {
+    UnusedLabel(String),
+    UnreachableCode,
+}
+
+impl fmt::Display for LexingWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexingWarning::UnusedLabel(name) => write!(f, "UnusedLabel `{}`", name),
+            LexingWarning::UnreachableCode => write!(f, "UnreachableCode"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BytecodeError {
+    NotBytecode,
+    UnsupportedVersion(u8),
+    UnexpectedEnd,
+    IllegalOpcode(u8),
+    IllegalTrapCode(u8),
+    IllegalSymbol,
+    TrailingBytes,
+    // the instruction at the index has an operand `Instruction::validate` rejects.
+    InvalidOperand(usize, OperandError),
+    // the handler installed for the trap code is not inside the program.
+    InvalidTrapHandler(u8, OperandError),
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BytecodeError::UnsupportedVersion(version) => {
+                write!(f, "UnsupportedVersion `{}`", version)
+            }
+            BytecodeError::IllegalOpcode(opcode) => write!(f, "IllegalOpcode `{}`", opcode),
+            BytecodeError::IllegalTrapCode(code) => write!(f, "IllegalTrapCode `{}`", code),
+            BytecodeError::InvalidOperand(address, err) => {
+                write!(f, "InvalidOperand at {}: {}", address, err)
+            }
+            BytecodeError::LimitExceeded(limit) => write!(f, "LimitExceeded: {}", limit),
+            BytecodeError::InvalidTrapHandler(code, err) => {
+                write!(f, "InvalidTrapHandler for {}: {}", code, err)
+            }
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// why a program can not be compiled.
+#[derive(Debug, PartialEq)]
+pub enum CompileError {
+    // natives are rust closures registered with the vm, which a compiled program has not.
+    NativeCall(usize),
+    // only the float backend is compiled.
+    IntegerArithmetic,
+    // a compiled program runs without limits.
+    Limits,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::NativeCall(address) => write!(f, "NativeCall at {}", address),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// why an operand used as a stack offset or an instruction index can not be used as one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperandError {
+    NonFiniteOperand(Float),
+    NonIntegralOperand(Float),
+    NegativeOperand(Float),
+    OperandOutOfRange(Float),
+    TargetOutOfBounds(usize),
+}
+
+impl fmt::Display for OperandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperandError::NonFiniteOperand(operand) => {
+                write!(f, "NonFiniteOperand `{:?}`", operand)
+            }
+            OperandError::NonIntegralOperand(operand) => {
+                write!(f, "NonIntegralOperand `{:?}`", operand)
+            }
+            OperandError::NegativeOperand(operand) => write!(f, "NegativeOperand `{:?}`", operand),
+            OperandError::OperandOutOfRange(operand) => {
+                write!(f, "OperandOutOfRange `{:?}`", operand)
+            }
+            OperandError::TargetOutOfBounds(target) => {
+                write!(f, "TargetOutOfBounds `{}`", target)
+            }
+        }
+    }
+}
+
+// an assembly error or warning together with the (1-based) source line it was found on.
+#[derive(Debug)]
+pub struct Diagnostic<T> {
+    pub line: usize,
+    pub kind: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Diagnostic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
 }
@@ -0,0 +1,265 @@
+use crate::{
+    error::LexingError,
+    global::Float,
+    lexer::{Token, Value},
+};
+
+// how deeply parentheses and unary operators may nest, so the evaluator's recursion stays
+// well within the stack.
+const MAX_NESTING: usize = 128;
+
+// looks up the value of a symbol token (a name, local name or numeric label reference).
+pub type Resolver<'a> = dyn Fn(&Token) -> Result<Value, LexingError> + 'a;
+
+// evaluates an operand expression at assemble time, resolving symbols through `resolve`.
+pub fn evaluate(tokens: &[Token], resolve: &Resolver) -> Result<Value, LexingError> {
+    if tokens.is_empty() {
+        return Err(LexingError::InvalidExpression);
+    }
+
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        depth: 0,
+        resolve,
+    };
+    let value = evaluator.bitwise_or()?;
+    if evaluator.position != evaluator.tokens.len() {
+        return Err(LexingError::InvalidExpression);
+    }
+    Ok(value)
+}
+
+// evaluates a comma separated list of expressions, as used by the `.bytes` and `.floats` directives.
+pub fn evaluate_list(tokens: &[Token], resolve: &Resolver) -> Result<Vec<Value>, LexingError> {
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        depth: 0,
+        resolve,
+    };
+
+    let mut values = vec![evaluator.bitwise_or()?];
+    while evaluator.peek() == Some(&Token::Comma) {
+        evaluator.advance();
+        values.push(evaluator.bitwise_or()?);
+    }
+    if evaluator.position != evaluator.tokens.len() {
+        return Err(LexingError::InvalidExpression);
+    }
+    Ok(values)
+}
+
+struct Evaluator<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    // unary operators and parentheses the evaluator is inside of.
+    depth: usize,
+    resolve: &'a Resolver<'a>,
+}
+
+impl Evaluator<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn bitwise_or(&mut self) -> Result<Value, LexingError> {
+        let mut left = self.bitwise_xor()?;
+        while self.peek() == Some(&Token::Pipe) {
+            self.advance();
+            let right = self.bitwise_xor()?;
+            left = Value::Integer(left.as_integer()? | right.as_integer()?);
+        }
+        Ok(left)
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Value, LexingError> {
+        let mut left = self.bitwise_and()?;
+        while self.peek() == Some(&Token::Caret) {
+            self.advance();
+            let right = self.bitwise_and()?;
+            left = Value::Integer(left.as_integer()? ^ right.as_integer()?);
+        }
+        Ok(left)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Value, LexingError> {
+        let mut left = self.shift()?;
+        while self.peek() == Some(&Token::Ampersand) {
+            self.advance();
+            let right = self.shift()?;
+            left = Value::Integer(left.as_integer()? & right.as_integer()?);
+        }
+        Ok(left)
+    }
+
+    fn shift(&mut self) -> Result<Value, LexingError> {
+        let mut left = self.additive()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::ShiftLeft) => Token::ShiftLeft,
+                Some(Token::ShiftRight) => Token::ShiftRight,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.additive()?;
+
+            let value = left.as_integer()?;
+            let amount: u32 = right
+                .as_integer()?
+                .try_into()
+                .map_err(|_| LexingError::ExpressionOverflow)?;
+            let result = if operator == Token::ShiftLeft {
+                let shifted = value
+                    .checked_shl(amount)
+                    .ok_or(LexingError::ExpressionOverflow)?;
+                // bits shifted out of the top are an overflow, not a silent truncation.
+                if shifted >> amount != value {
+                    return Err(LexingError::ExpressionOverflow);
+                }
+                shifted
+            } else {
+                value
+                    .checked_shr(amount)
+                    .ok_or(LexingError::ExpressionOverflow)?
+            };
+            left = Value::Integer(result);
+        }
+    }
+
+    fn additive(&mut self) -> Result<Value, LexingError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Plus) => Token::Plus,
+                Some(Token::Minus) => Token::Minus,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.multiplicative()?;
+
+            left = match (left, right) {
+                (Value::Integer(a), Value::Integer(b)) => {
+                    let result = if operator == Token::Plus {
+                        a.checked_add(b)
+                    } else {
+                        a.checked_sub(b)
+                    };
+                    Value::Integer(result.ok_or(LexingError::ExpressionOverflow)?)
+                }
+                (a, b) => {
+                    let (a, b) = (a.as_float(), b.as_float());
+                    if operator == Token::Plus {
+                        float_result(a + b, a, b)?
+                    } else {
+                        float_result(a - b, a, b)?
+                    }
+                }
+            };
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Value, LexingError> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Star) => Token::Star,
+                Some(Token::Slash) => Token::Slash,
+                Some(Token::Percent) => Token::Percent,
+                _ => return Ok(left),
+            };
+            self.advance();
+            let right = self.unary()?;
+
+            left = match (left, right) {
+                (Value::Integer(a), Value::Integer(b)) => {
+                    if operator != Token::Star && b == 0 {
+                        return Err(LexingError::ExpressionDivisionByZero);
+                    }
+                    let result = match operator {
+                        Token::Star => a.checked_mul(b),
+                        Token::Slash => a.checked_div(b),
+                        _ => a.checked_rem(b),
+                    };
+                    Value::Integer(result.ok_or(LexingError::ExpressionOverflow)?)
+                }
+                (a, b) => {
+                    let (a, b) = (a.as_float(), b.as_float());
+                    if operator != Token::Star && b == 0. {
+                        return Err(LexingError::ExpressionDivisionByZero);
+                    }
+                    match operator {
+                        Token::Star => float_result(a * b, a, b)?,
+                        Token::Slash => float_result(a / b, a, b)?,
+                        _ => float_result(a % b, a, b)?,
+                    }
+                }
+            };
+        }
+    }
+
+    // every level of nesting goes through here.
+    fn unary(&mut self) -> Result<Value, LexingError> {
+        if self.depth == MAX_NESTING {
+            return Err(LexingError::InvalidExpression);
+        }
+        self.depth += 1;
+        let value = self.unary_operation();
+        self.depth -= 1;
+        value
+    }
+
+    fn unary_operation(&mut self) -> Result<Value, LexingError> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.advance();
+                match self.unary()? {
+                    Value::Integer(value) => Ok(Value::Integer(
+                        value.checked_neg().ok_or(LexingError::ExpressionOverflow)?,
+                    )),
+                    Value::Float(value) => Ok(Value::Float(-value)),
+                }
+            }
+            Some(Token::Plus) => {
+                self.advance();
+                self.unary()
+            }
+            Some(Token::Tilde) => {
+                self.advance();
+                Ok(Value::Integer(!self.unary()?.as_integer()?))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, LexingError> {
+        match self.advance() {
+            Some(Token::Number(value)) => Ok(value),
+            Some(symbol @ (Token::Name(_) | Token::LocalName(_) | Token::NumericLabel { .. })) => {
+                (self.resolve)(&symbol)
+            }
+            Some(Token::LeftParen) => {
+                let value = self.bitwise_or()?;
+                if self.advance() != Some(Token::RightParen) {
+                    return Err(LexingError::InvalidExpression);
+                }
+                Ok(value)
+            }
+            _ => Err(LexingError::InvalidExpression),
+        }
+    }
+}
+
+// a finite computation that ends up infinite has overflowed.
+fn float_result(value: Float, a: Float, b: Float) -> Result<Value, LexingError> {
+    if value.is_infinite() && a.is_finite() && b.is_finite() {
+        return Err(LexingError::ExpressionOverflow);
+    }
+    Ok(Value::Float(value))
+}
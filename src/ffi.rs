@@ -0,0 +1,197 @@
+// the c api, declared in include/uvm.h (which tests/ffi.rs generates from its table of it).
+// pointers passed in must be valid for the call: a vm from `uvm_new` that was not freed yet,
+// and buffers of at least the given length. strings returned stay valid until the next call
+// on the same vm.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    ffi::{c_char, c_void, CString},
+    io::{self, sink, stdout, Write},
+    slice,
+};
+
+use crate::{
+    bytecode::is_bytecode,
+    core::{RunStatus, UVM},
+};
+
+pub const UVM_OK: i32 = 0;
+pub const UVM_HALTED: i32 = 1;
+pub const UVM_PAUSED: i32 = 2;
+pub const UVM_CANCELLED: i32 = 3;
+pub const UVM_TIMED_OUT: i32 = 4;
+pub const UVM_TRAPPED: i32 = -1;
+pub const UVM_ERROR: i32 = -2;
+
+// receives everything the program prints, `length` bytes of utf-8 at a time.
+pub type UvmOutput = unsafe extern "C" fn(user_data: *mut c_void, bytes: *const u8, length: usize);
+
+pub struct UvmVm {
+    vm: UVM,
+    last_error: Option<CString>,
+}
+
+impl UvmVm {
+    fn fail(&mut self, status: i32, message: String) -> i32 {
+        // a message can not contain a nul for c, so it is cut off there.
+        let message = match message.find('\0') {
+            Some(end) => &message[..end],
+            None => &message,
+        };
+        self.last_error = CString::new(message).ok();
+        status
+    }
+}
+
+struct Callback {
+    function: UvmOutput,
+    user_data: *mut c_void,
+}
+
+impl Write for Callback {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        unsafe { (self.function)(self.user_data, bytes.as_ptr(), bytes.len()) };
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// a new vm without a program, printing to stdout.
+#[no_mangle]
+pub extern "C" fn uvm_new() -> *mut UvmVm {
+    Box::into_raw(Box::new(UvmVm {
+        vm: UVM::new(),
+        last_error: None,
+    }))
+}
+
+// frees a vm; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_free(vm: *mut UvmVm) {
+    if !vm.is_null() {
+        drop(Box::from_raw(vm));
+    }
+}
+
+// replaces the vm's program (and everything it did) with bytecode, or with assembly when the
+// bytes do not start with the bytecode header, and returns UVM_OK or UVM_ERROR.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_load(vm: *mut UvmVm, bytes: *const u8, length: usize) -> i32 {
+    let vm = &mut *vm;
+    let bytes = if length == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(bytes, length)
+    };
+
+    vm.last_error = None;
+    let output = vm.vm.set_output(Box::new(sink()));
+    vm.vm = UVM::new();
+    vm.vm.set_output(output);
+    if is_bytecode(bytes) {
+        return match vm.vm.load_bytecode(bytes) {
+            Some(err) => vm.fail(UVM_ERROR, format!("BytecodeError: {}", err)),
+            None => UVM_OK,
+        };
+    }
+    let source = match std::str::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(_) => return vm.fail(UVM_ERROR, String::from("source is not valid utf-8")),
+    };
+    match vm.vm.load_program(source) {
+        Some(errors) => {
+            let errors: Vec<String> = errors
+                .iter()
+                .map(|err| format!("LexingError: {}", err))
+                .collect();
+            vm.fail(UVM_ERROR, errors.join("\n"))
+        }
+        None => UVM_OK,
+    }
+}
+
+// executes a single instruction. returns UVM_HALTED once the program halted, UVM_PAUSED when
+// there is more to execute and UVM_TRAPPED when the instruction trapped.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_step(vm: *mut UvmVm) -> i32 {
+    let vm = &mut *vm;
+    vm.last_error = None;
+    if vm.vm.is_halted() {
+        return UVM_HALTED;
+    }
+    if let Some(context) = vm.vm.step() {
+        return vm.fail(UVM_TRAPPED, format!("Trap: {}", context));
+    }
+    if vm.vm.is_halted() {
+        UVM_HALTED
+    } else {
+        UVM_PAUSED
+    }
+}
+
+// executes at most `budget` instructions, or until the program stops when it is 0. returns
+// UVM_HALTED, UVM_PAUSED, UVM_CANCELLED, UVM_TIMED_OUT or UVM_TRAPPED.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_run(vm: *mut UvmVm, budget: u64) -> i32 {
+    let vm = &mut *vm;
+    vm.last_error = None;
+    let result = if budget == 0 {
+        vm.vm.execute()
+    } else {
+        vm.vm.execute_for(budget)
+    };
+    match result {
+        Ok(RunStatus::Halted(_)) => UVM_HALTED,
+        Ok(RunStatus::Paused) => UVM_PAUSED,
+        Ok(RunStatus::Cancelled) => UVM_CANCELLED,
+        Ok(RunStatus::TimedOut) => UVM_TIMED_OUT,
+        Err(context) => vm.fail(UVM_TRAPPED, format!("Trap: {}", context)),
+    }
+}
+
+// the code given to `hlt`, once the program halted.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_exit_code(vm: *const UvmVm) -> i32 {
+    (*vm).vm.exit_code() as i32
+}
+
+// copies up to `capacity` values from the bottom of the stack into `values` and returns the
+// number of values on the stack, so a null buffer asks for the size.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_stack(vm: *const UvmVm, values: *mut f64, capacity: usize) -> usize {
+    let stack = (*vm).vm.stack();
+    if !values.is_null() {
+        let count = capacity.min(stack.len());
+        slice::from_raw_parts_mut(values, count).copy_from_slice(&stack[..count]);
+    }
+    stack.len()
+}
+
+// sends the program's output to `output` instead of stdout, or back to stdout when it is null.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_set_output(
+    vm: *mut UvmVm,
+    output: Option<UvmOutput>,
+    user_data: *mut c_void,
+) {
+    let vm = &mut *vm;
+    match output {
+        Some(function) => vm.vm.set_output(Box::new(Callback {
+            function,
+            user_data,
+        })),
+        None => vm.vm.set_output(Box::new(stdout())),
+    };
+}
+
+// why the last call on the vm failed, or null when it did not.
+#[no_mangle]
+pub unsafe extern "C" fn uvm_last_error(vm: *const UvmVm) -> *const c_char {
+    match &(*vm).last_error {
+        Some(message) => message.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    cell::RefCell,
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    core::{Engine, RunStatus, UVM},
+    limits::VmLimits,
+};
+
+// golden programs are uvm sources whose comments say what running them must produce:
+//   ; expect-stdout: <line>   a line of output, in order (the newline is implied)
+//   ; expect-error: <error>   the assembly error or trap the program fails with
+//   ; expect-exit: <code>     the code given to `hlt` (0 when no error is expected)
+// the output is always compared, so a program without `expect-stdout` must print nothing.
+
+// how many instructions a golden program may execute, so a broken one can not hang the run.
+const INSTRUCTION_LIMIT: u64 = 10_000_000;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Expectations {
+    pub stdout: String,
+    pub error: Option<String>,
+    pub exit_code: Option<u8>,
+}
+
+impl Expectations {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut expectations = Expectations::default();
+        for (index, line) in source.lines().enumerate() {
+            let comment = match line.trim_start().strip_prefix(';') {
+                Some(comment) => comment.trim_start(),
+                None => continue,
+            };
+            if let Some(text) = comment.strip_prefix("expect-stdout:") {
+                let text = text.strip_prefix(' ').unwrap_or(text);
+                expectations.stdout.push_str(text);
+                expectations.stdout.push('\n');
+            } else if let Some(error) = comment.strip_prefix("expect-error:") {
+                expectations.error = Some(error.trim().to_string());
+            } else if let Some(code) = comment.strip_prefix("expect-exit:") {
+                match code.trim().parse() {
+                    Ok(code) => expectations.exit_code = Some(code),
+                    Err(_) => return Err(format!("line {}: invalid exit code", index + 1)),
+                }
+            }
+        }
+
+        if expectations.error.is_none() && expectations.exit_code.is_none() {
+            expectations.exit_code = Some(0);
+        }
+        Ok(expectations)
+    }
+}
+
+// what running a program produced.
+#[derive(Debug, Default, PartialEq)]
+pub struct Outcome {
+    pub stdout: String,
+    // the first assembly error, or the trap the program stopped with.
+    pub error: Option<String>,
+    pub exit_code: Option<u8>,
+}
+
+impl Outcome {
+    pub fn run(source: &str) -> Self {
+        Self::run_with(source, Engine::Interpreter)
+    }
+
+    pub fn run_with(source: &str, engine: Engine) -> Self {
+        let mut vm = UVM::new();
+        vm.set_engine(engine);
+        let stdout = SharedBuffer::default();
+        vm.set_output(Box::new(stdout.clone()));
+        vm.set_limits(VmLimits {
+            max_instructions: Some(INSTRUCTION_LIMIT),
+            ..VmLimits::default()
+        });
+
+        let mut outcome = Outcome::default();
+        if let Some(errors) = vm.load_program(source) {
+            outcome.error = Some(errors[0].kind.to_string());
+            return outcome;
+        }
+        match vm.execute() {
+            Ok(RunStatus::Halted(code)) => outcome.exit_code = Some(code),
+            Ok(status) => outcome.error = Some(format!("{:?}", status)),
+            Err(context) => outcome.error = Some(context.trap.to_string()),
+        }
+        outcome.stdout = stdout.text();
+        outcome
+    }
+
+    // describes every way the outcome differs from the expectations, or None when it does not.
+    pub fn compare(&self, expectations: &Expectations) -> Option<String> {
+        let mut differences = Vec::new();
+
+        match (&expectations.error, &self.error) {
+            (None, None) => {}
+            (Some(expected), Some(actual)) if error_matches(expected, actual) => {}
+            (expected, actual) => differences.push(format!(
+                "error: expected {}, got {}",
+                expected.as_deref().unwrap_or("none"),
+                actual.as_deref().unwrap_or("none")
+            )),
+        }
+        if let Some(expected) = expectations.exit_code {
+            if self.exit_code != Some(expected) {
+                let actual = match self.exit_code {
+                    Some(code) => code.to_string(),
+                    None => String::from("none"),
+                };
+                differences.push(format!("exit: expected {}, got {}", expected, actual));
+            }
+        }
+        if self.stdout != expectations.stdout {
+            differences.push(format!(
+                "stdout:\n{}",
+                diff(&expectations.stdout, &self.stdout)
+            ));
+        }
+
+        if differences.is_empty() {
+            None
+        } else {
+            Some(differences.join("\n"))
+        }
+    }
+}
+
+// `StackUnderflow` matches the trap of the same name, `UndefinedSymbol` also matches
+// "UndefinedSymbol `x`", and the full text matches too.
+fn error_matches(expected: &str, actual: &str) -> bool {
+    actual == expected
+        || actual.starts_with(&format!("{} ", expected))
+        || actual.starts_with(&format!("{}:", expected))
+}
+
+// a line by line diff: lines only expected start with `-`, lines only produced with `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut output = Vec::new();
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(a), Some(b)) if a == b => output.push(format!("  {}", a)),
+            (a, b) => {
+                if let Some(a) = a {
+                    output.push(format!("- {}", a));
+                }
+                if let Some(b) = b {
+                    output.push(format!("+ {}", b));
+                }
+            }
+        }
+    }
+    output.join("\n")
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub passed: Vec<PathBuf>,
+    pub failures: Vec<Failure>,
+}
+
+// runs every `.uasm` file in a directory (not its subdirectories), in name order.
+pub fn run_directory(directory: &Path) -> io::Result<Report> {
+    run_directory_with(directory, Engine::Interpreter)
+}
+
+pub fn run_directory_with(directory: &Path, engine: Engine) -> io::Result<Report> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "uasm")
+        })
+        .collect();
+    paths.sort();
+
+    let mut report = Report::default();
+    for path in paths {
+        let source = fs::read_to_string(&path)?;
+        let result =
+            Expectations::parse(&source).and_then(|expectations| {
+                match Outcome::run_with(&source, engine).compare(&expectations) {
+                    Some(differences) => Err(differences),
+                    None => Ok(()),
+                }
+            });
+        match result {
+            Ok(()) => report.passed.push(path),
+            Err(message) => report.failures.push(Failure { path, message }),
+        }
+    }
+    Ok(report)
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+}
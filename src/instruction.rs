@@ -1,6 +1,8 @@
-use crate::global::Float;
+use std::fmt;
 
-#[derive(Debug)]
+use crate::{error::OperandError, global::Float};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InstructionType {
     Push,
     Pop,
@@ -10,6 +12,18 @@ pub enum InstructionType {
 
     Jump,
     JumpIf,
+    Call,
+    Return,
+
+    Enter,
+    Leave,
+    LoadLocal,
+    StoreLocal,
+    LoadArgument,
+
+    Try,
+    EndTry,
+    Throw,
 
     Equal,
     GreaterEqual,
@@ -20,14 +34,188 @@ pub enum InstructionType {
     Multiply,
     Divide,
 
+    CallNative,
+
     Dump,
     Output,
     Outputf,
+    OutputString,
+    OutputCharacter,
 
     Halt,
 }
 
-#[derive(Debug)]
+impl InstructionType {
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        match mnemonic {
+            "push" => Some(InstructionType::Push),
+            "pop" => Some(InstructionType::Pop),
+            "dup" => Some(InstructionType::Duplicate),
+            "swp" => Some(InstructionType::Swap),
+            "jmp" => Some(InstructionType::Jump),
+            "jmpif" => Some(InstructionType::JumpIf),
+            "call" => Some(InstructionType::Call),
+            "ret" => Some(InstructionType::Return),
+            "enter" => Some(InstructionType::Enter),
+            "leave" => Some(InstructionType::Leave),
+            "lload" => Some(InstructionType::LoadLocal),
+            "lstore" => Some(InstructionType::StoreLocal),
+            "aload" => Some(InstructionType::LoadArgument),
+            "try" => Some(InstructionType::Try),
+            "endtry" => Some(InstructionType::EndTry),
+            "throw" => Some(InstructionType::Throw),
+            "eql" => Some(InstructionType::Equal),
+            "geql" => Some(InstructionType::GreaterEqual),
+            "not" => Some(InstructionType::Not),
+            "add" => Some(InstructionType::Add),
+            "sub" => Some(InstructionType::Subtract),
+            "mul" => Some(InstructionType::Multiply),
+            "div" => Some(InstructionType::Divide),
+            "callnative" => Some(InstructionType::CallNative),
+            "dmp" => Some(InstructionType::Dump),
+            "out" => Some(InstructionType::Output),
+            "outf" => Some(InstructionType::Outputf),
+            "outs" => Some(InstructionType::OutputString),
+            "outc" => Some(InstructionType::OutputCharacter),
+            "hlt" => Some(InstructionType::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            InstructionType::Push => "push",
+            InstructionType::Pop => "pop",
+            InstructionType::Duplicate => "dup",
+            InstructionType::Swap => "swp",
+            InstructionType::Jump => "jmp",
+            InstructionType::JumpIf => "jmpif",
+            InstructionType::Call => "call",
+            InstructionType::Return => "ret",
+            InstructionType::Enter => "enter",
+            InstructionType::Leave => "leave",
+            InstructionType::LoadLocal => "lload",
+            InstructionType::StoreLocal => "lstore",
+            InstructionType::LoadArgument => "aload",
+            InstructionType::Try => "try",
+            InstructionType::EndTry => "endtry",
+            InstructionType::Throw => "throw",
+            InstructionType::Equal => "eql",
+            InstructionType::GreaterEqual => "geql",
+            InstructionType::Not => "not",
+            InstructionType::Add => "add",
+            InstructionType::Subtract => "sub",
+            InstructionType::Multiply => "mul",
+            InstructionType::Divide => "div",
+            InstructionType::CallNative => "callnative",
+            InstructionType::Dump => "dmp",
+            InstructionType::Output => "out",
+            InstructionType::Outputf => "outf",
+            InstructionType::OutputString => "outs",
+            InstructionType::OutputCharacter => "outc",
+            InstructionType::Halt => "hlt",
+        }
+    }
+
+    // the number an instruction is stored as in bytecode.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            InstructionType::Push => 0,
+            InstructionType::Pop => 1,
+            InstructionType::Duplicate => 2,
+            InstructionType::Swap => 3,
+            InstructionType::Jump => 4,
+            InstructionType::JumpIf => 5,
+            InstructionType::Call => 6,
+            InstructionType::Return => 7,
+            InstructionType::Enter => 8,
+            InstructionType::Leave => 9,
+            InstructionType::LoadLocal => 10,
+            InstructionType::StoreLocal => 11,
+            InstructionType::LoadArgument => 12,
+            InstructionType::Try => 13,
+            InstructionType::EndTry => 14,
+            InstructionType::Throw => 15,
+            InstructionType::Equal => 16,
+            InstructionType::GreaterEqual => 17,
+            InstructionType::Not => 18,
+            InstructionType::Add => 19,
+            InstructionType::Subtract => 20,
+            InstructionType::Multiply => 21,
+            InstructionType::Divide => 22,
+            InstructionType::CallNative => 23,
+            InstructionType::Dump => 24,
+            InstructionType::Output => 25,
+            InstructionType::Outputf => 26,
+            InstructionType::OutputString => 27,
+            InstructionType::OutputCharacter => 28,
+            InstructionType::Halt => 29,
+        }
+    }
+
+    pub fn from_opcode(opcode: u8) -> Option<Self> {
+        match opcode {
+            0 => Some(InstructionType::Push),
+            1 => Some(InstructionType::Pop),
+            2 => Some(InstructionType::Duplicate),
+            3 => Some(InstructionType::Swap),
+            4 => Some(InstructionType::Jump),
+            5 => Some(InstructionType::JumpIf),
+            6 => Some(InstructionType::Call),
+            7 => Some(InstructionType::Return),
+            8 => Some(InstructionType::Enter),
+            9 => Some(InstructionType::Leave),
+            10 => Some(InstructionType::LoadLocal),
+            11 => Some(InstructionType::StoreLocal),
+            12 => Some(InstructionType::LoadArgument),
+            13 => Some(InstructionType::Try),
+            14 => Some(InstructionType::EndTry),
+            15 => Some(InstructionType::Throw),
+            16 => Some(InstructionType::Equal),
+            17 => Some(InstructionType::GreaterEqual),
+            18 => Some(InstructionType::Not),
+            19 => Some(InstructionType::Add),
+            20 => Some(InstructionType::Subtract),
+            21 => Some(InstructionType::Multiply),
+            22 => Some(InstructionType::Divide),
+            23 => Some(InstructionType::CallNative),
+            24 => Some(InstructionType::Dump),
+            25 => Some(InstructionType::Output),
+            26 => Some(InstructionType::Outputf),
+            27 => Some(InstructionType::OutputString),
+            28 => Some(InstructionType::OutputCharacter),
+            29 => Some(InstructionType::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn has_operand(&self) -> bool {
+        matches!(
+            self,
+            InstructionType::Push
+                | InstructionType::Duplicate
+                | InstructionType::Swap
+                | InstructionType::Jump
+                | InstructionType::JumpIf
+                | InstructionType::Call
+                | InstructionType::Enter
+                | InstructionType::LoadLocal
+                | InstructionType::StoreLocal
+                | InstructionType::LoadArgument
+                | InstructionType::Try
+                | InstructionType::CallNative
+                | InstructionType::Halt
+        )
+    }
+
+    pub fn operand_is_optional(&self) -> bool {
+        matches!(self, InstructionType::Halt)
+    }
+}
+
+const MAX_OFFSET: Float = (1u64 << 53) as Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Instruction {
     pub instruction_type: InstructionType,
     pub operand: Option<Float>,
@@ -40,4 +228,72 @@ impl Instruction {
             operand,
         }
     }
+
+    // checks the operands that are used as indices: `dup` and `swp` offsets into the stack,
+    // and `jmp`, `jmpif`, `call` and `try` targets, which must be inside the program.
+    // a missing operand is left to the instruction itself to trap on.
+    pub fn validate(&self, program_length: usize) -> Option<OperandError> {
+        let operand = match (self.instruction_type, self.operand) {
+            (
+                InstructionType::Duplicate
+                | InstructionType::Swap
+                | InstructionType::Jump
+                | InstructionType::JumpIf
+                | InstructionType::Call
+                | InstructionType::Try,
+                Some(operand),
+            ) => operand,
+            _ => return None,
+        };
+
+        if !operand.is_finite() {
+            return Some(OperandError::NonFiniteOperand(operand));
+        }
+        if operand.fract() != 0. {
+            return Some(OperandError::NonIntegralOperand(operand));
+        }
+        if operand < 0. {
+            return Some(OperandError::NegativeOperand(operand));
+        }
+        match self.instruction_type {
+            // `swp 0` would swap the top with itself.
+            InstructionType::Swap if operand == 0. => {
+                Some(OperandError::OperandOutOfRange(operand))
+            }
+            // past 2^53 not every integer is a float, so the offset may not be the one written.
+            InstructionType::Duplicate | InstructionType::Swap => {
+                if operand > MAX_OFFSET {
+                    Some(OperandError::OperandOutOfRange(operand))
+                } else {
+                    None
+                }
+            }
+            _ => {
+                if operand >= program_length as Float {
+                    Some(OperandError::TargetOutOfBounds(operand as usize))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+// whether two programs are the same down to the bits of their operands, which `==` on the
+// operands is not for nan and -0.
+pub(crate) fn identical(a: &[Instruction], b: &[Instruction]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.instruction_type == b.instruction_type
+                && a.operand.map(Float::to_bits) == b.operand.map(Float::to_bits)
+        })
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.operand {
+            Some(operand) => write!(f, "{} {}", self.instruction_type.mnemonic(), operand),
+            None => write!(f, "{}", self.instruction_type.mnemonic()),
+        }
+    }
 }
@@ -0,0 +1,515 @@
+// compiles a program to x86-64 code. the values stay on the vm's own stack (its buffer, length
+// and room are kept in registers), jumps become native branches and everything else, from
+// output to traps, calls back into rust, which executes that instruction with the interpreter.
+// the code can be entered at any instruction, through a table of where each one starts.
+use std::{
+    collections::HashMap,
+    ffi::c_void,
+    ptr::{self, null_mut},
+};
+
+use crate::{
+    global::Float,
+    instruction::{identical, Instruction, InstructionType},
+    numeric::Numeric,
+};
+
+// what the step callback returns to stop the code.
+pub const STOP: u64 = u64::MAX;
+
+const PROT_READ: i32 = 1;
+const PROT_WRITE: i32 = 2;
+const PROT_EXEC: i32 = 4;
+const MAP_PRIVATE: i32 = 2;
+const MAP_ANONYMOUS: i32 = 0x20;
+
+extern "C" {
+    fn mmap(
+        address: *mut c_void,
+        length: usize,
+        protection: i32,
+        flags: i32,
+        fd: i32,
+        offset: i64,
+    ) -> *mut c_void;
+    fn mprotect(address: *mut c_void, length: usize, protection: i32) -> i32;
+    fn munmap(address: *mut c_void, length: usize) -> i32;
+}
+
+// the registers the code works with, as they are when it calls back into rust. the callback
+// may change all of them, e.g. moving the stack when it grows.
+#[repr(C)]
+pub struct Machine {
+    pub stack: *mut Float,
+    pub length: usize,
+    // how long the stack may get before a push has to go through the interpreter.
+    pub room: usize,
+    // instructions left to execute; at 0 the code calls back without executing anything.
+    pub fuel: u64,
+    trampoline: extern "sysv64" fn(*mut Machine, u64) -> u64,
+    callback: *mut c_void,
+}
+
+impl Machine {
+    pub fn new(stack: &mut Vec<Float>, room: usize, fuel: u64) -> Self {
+        Self {
+            stack: stack.as_mut_ptr(),
+            length: stack.len(),
+            room,
+            fuel,
+            trampoline,
+            callback: null_mut(),
+        }
+    }
+}
+
+type Callback<'a> = &'a mut dyn FnMut(&mut Machine, usize) -> u64;
+
+extern "sysv64" fn trampoline(machine: *mut Machine, address: u64) -> u64 {
+    unsafe {
+        let callback = &mut *((*machine).callback as *mut Callback);
+        callback(&mut *machine, address as usize)
+    }
+}
+
+pub struct Code {
+    memory: *mut u8,
+    size: usize,
+    entry: usize,
+    // what was compiled, so the code is only reused for the same program.
+    program: Vec<Instruction>,
+    numeric: Numeric,
+}
+
+impl Drop for Code {
+    fn drop(&mut self) {
+        unsafe { munmap(self.memory as *mut c_void, self.size) };
+    }
+}
+
+impl Code {
+    // None when the code can not be mapped executable, or when the program is too long for
+    // `dispatch` to compare an index with its length (the immediate is sign-extended).
+    pub fn compile(program: &[Instruction], numeric: Numeric) -> Option<Self> {
+        if program.len() > i32::MAX as usize {
+            return None;
+        }
+        let mut assembler = Assembler::default();
+        assembler.program(program, numeric);
+        let (bytes, table, entry) = assembler.finish();
+
+        let size = bytes.len().max(1);
+        let memory = unsafe {
+            mmap(
+                null_mut(),
+                size,
+                PROT_READ | PROT_WRITE,
+                MAP_PRIVATE | MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if memory as isize == -1 {
+            return None;
+        }
+        let memory = memory as *mut u8;
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), memory, bytes.len());
+            // the table holds absolute addresses, known now that the code has its place.
+            let (start, offsets) = table;
+            for (index, offset) in offsets.iter().enumerate() {
+                let address = memory as u64 + *offset as u64;
+                ptr::copy_nonoverlapping(
+                    address.to_le_bytes().as_ptr(),
+                    memory.add(start + index * 8),
+                    8,
+                );
+            }
+            if mprotect(memory as *mut c_void, size, PROT_READ | PROT_EXEC) != 0 {
+                munmap(memory as *mut c_void, size);
+                return None;
+            }
+        }
+        Some(Self {
+            memory,
+            size,
+            entry,
+            program: program.to_vec(),
+            numeric,
+        })
+    }
+
+    pub fn is_for(&self, program: &[Instruction], numeric: Numeric) -> bool {
+        self.numeric == numeric && identical(&self.program, program)
+    }
+
+    // executes from `address` on. `step` is called with the registers for every instruction
+    // the code does not execute itself (and when the fuel runs out), and returns where to
+    // continue or STOP, which ends the run.
+    pub fn run(
+        &self,
+        machine: &mut Machine,
+        address: usize,
+        mut step: impl FnMut(&mut Machine, usize) -> u64,
+    ) {
+        let mut callback: Callback = &mut step;
+        machine.callback = &mut callback as *mut Callback as *mut c_void;
+        let entry: extern "sysv64" fn(*mut Machine, u64) -> u64 =
+            unsafe { std::mem::transmute(self.memory.add(self.entry)) };
+        entry(machine, address as u64);
+        machine.callback = null_mut();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Label {
+    Instruction(usize),
+    // executes the instruction through the callback instead.
+    Slow(usize),
+    Step,
+    Done,
+    Table,
+}
+
+// registers: rbx holds the machine, r12 the stack, r13 its length, r14 the room, r15 the fuel.
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u8>,
+    labels: HashMap<Label, usize>,
+    // where a rel32 to a label has to be filled in.
+    fixups: Vec<(usize, Label)>,
+    slow: Vec<usize>,
+    length: usize,
+}
+
+// the machine's fields, as offsets from rbx.
+const LENGTH: u8 = 8;
+const FUEL: u8 = 24;
+const TRAMPOLINE: u8 = 32;
+
+const ONE: u64 = 0x3ff0_0000_0000_0000;
+
+impl Assembler {
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn label(&mut self, label: Label) {
+        self.labels.insert(label, self.code.len());
+    }
+
+    fn rel32(&mut self, label: Label) {
+        self.fixups.push((self.code.len(), label));
+        self.emit(&[0; 4]);
+    }
+
+    fn jump(&mut self, label: Label) {
+        self.emit(&[0xe9]);
+        self.rel32(label);
+    }
+
+    // a conditional jump, `condition` being the second opcode byte (0x84 is je).
+    fn jump_if(&mut self, condition: u8, label: Label) {
+        self.emit(&[0x0f, condition]);
+        self.rel32(label);
+    }
+
+    // goes to the callback for the instruction when the condition holds.
+    fn slow_if(&mut self, condition: u8, address: usize) {
+        // the checks of an instruction come one after the other.
+        if self.slow.last() != Some(&address) {
+            self.slow.push(address);
+        }
+        self.jump_if(condition, Label::Slow(address));
+    }
+
+    // [r12 + r13 * 8 + displacement], the value `depth` places below the top for depth 1.
+    fn stack_operand(&mut self, depth: usize) {
+        let displacement = -8 * depth as i32;
+        self.emit(&[0xec]);
+        self.emit(&displacement.to_le_bytes());
+    }
+
+    fn load_registers(&mut self) {
+        // mov r12, [rbx]; mov r13, [rbx + 8]; mov r14, [rbx + 16]; mov r15, [rbx + 24]
+        self.emit(&[0x4c, 0x8b, 0x63, 0x00]);
+        self.emit(&[0x4c, 0x8b, 0x6b, LENGTH]);
+        self.emit(&[0x4c, 0x8b, 0x73, 0x10]);
+        self.emit(&[0x4c, 0x8b, 0x7b, FUEL]);
+    }
+
+    fn program(&mut self, program: &[Instruction], numeric: Numeric) {
+        self.length = program.len();
+        for (address, instruction) in program.iter().enumerate() {
+            self.label(Label::Instruction(address));
+            if !self.instruction(address, instruction, numeric) {
+                self.call_back(address);
+            }
+        }
+        // running off the end traps, like jumping anywhere past it.
+        self.label(Label::Instruction(program.len()));
+        self.call_back(program.len());
+
+        let slow = std::mem::take(&mut self.slow);
+        for address in slow {
+            self.label(Label::Slow(address));
+            self.call_back(address);
+        }
+
+        // step: hands the instruction at esi to the callback, then continues where it says.
+        self.label(Label::Step);
+        // mov [rbx + 8], r13; mov [rbx + 24], r15; mov rdi, rbx; call [rbx + 32]
+        self.emit(&[0x4c, 0x89, 0x6b, LENGTH]);
+        self.emit(&[0x4c, 0x89, 0x7b, FUEL]);
+        self.emit(&[0x48, 0x89, 0xdf]);
+        self.emit(&[0xff, 0x53, TRAMPOLINE]);
+        self.load_registers();
+        // cmp rax, -1; je done
+        self.emit(&[0x48, 0x83, 0xf8, 0xff]);
+        self.jump_if(0x84, Label::Done);
+        self.dispatch();
+    }
+
+    // continues at the instruction in rax.
+    fn dispatch(&mut self) {
+        // cmp rax, length; jbe table; mov rsi, rax; jmp step (past the end, which traps)
+        self.emit(&[0x48, 0x3d]);
+        self.emit(&(self.length as u32).to_le_bytes());
+        self.emit(&[0x76, 0x08]);
+        self.emit(&[0x48, 0x89, 0xc6]);
+        self.jump(Label::Step);
+        // table: lea rcx, [rip + table]; jmp [rcx + rax * 8]
+        self.emit(&[0x48, 0x8d, 0x0d]);
+        self.rel32(Label::Table);
+        self.emit(&[0xff, 0x24, 0xc1]);
+    }
+
+    fn call_back(&mut self, address: usize) {
+        // mov esi, address; jmp step
+        self.emit(&[0xbe]);
+        self.emit(&(address as u32).to_le_bytes());
+        self.jump(Label::Step);
+    }
+
+    // the native code for an instruction, or false to leave it to the callback.
+    fn instruction(&mut self, address: usize, instruction: &Instruction, numeric: Numeric) -> bool {
+        let index = crate::core::index_operand(instruction.operand);
+        // offsets below the top have to fit a displacement.
+        let small = |index: usize| index < 1 << 28;
+        let native = match instruction.instruction_type {
+            InstructionType::Push => numeric == Numeric::Float && instruction.operand.is_some(),
+            InstructionType::Duplicate => index.is_some_and(small),
+            InstructionType::Swap => index.is_some_and(|offset| offset > 0 && small(offset)),
+            InstructionType::Jump | InstructionType::JumpIf => {
+                index.is_some_and(|target| target < self.length)
+            }
+            InstructionType::Add
+            | InstructionType::Subtract
+            | InstructionType::Multiply
+            | InstructionType::Divide => numeric == Numeric::Float,
+            InstructionType::Pop
+            | InstructionType::Equal
+            | InstructionType::GreaterEqual
+            | InstructionType::Not => true,
+            _ => false,
+        };
+        if !native {
+            return false;
+        }
+
+        // test r15, r15: without fuel the callback decides.
+        self.emit(&[0x4d, 0x85, 0xff]);
+        self.slow_if(0x84, address);
+
+        match instruction.instruction_type {
+            InstructionType::Push => {
+                // cmp r13, r14; jae slow
+                self.emit(&[0x4d, 0x39, 0xf5]);
+                self.slow_if(0x83, address);
+                self.consume_fuel();
+                // mov rax, value; mov [r12 + r13 * 8], rax; inc r13
+                self.emit(&[0x48, 0xb8]);
+                self.emit(&instruction.operand.unwrap().to_bits().to_le_bytes());
+                self.emit(&[0x4b, 0x89, 0x04, 0xec]);
+                self.emit(&[0x49, 0xff, 0xc5]);
+            }
+            InstructionType::Pop => {
+                self.require(1, address);
+                self.consume_fuel();
+                // dec r13
+                self.emit(&[0x49, 0xff, 0xcd]);
+            }
+            InstructionType::Duplicate => {
+                let offset = index.unwrap();
+                self.require(offset + 1, address);
+                self.emit(&[0x4d, 0x39, 0xf5]);
+                self.slow_if(0x83, address);
+                self.consume_fuel();
+                // mov rax, [value]; mov [r12 + r13 * 8], rax; inc r13
+                self.emit(&[0x4b, 0x8b, 0x84]);
+                self.stack_operand(offset + 1);
+                self.emit(&[0x4b, 0x89, 0x04, 0xec]);
+                self.emit(&[0x49, 0xff, 0xc5]);
+            }
+            InstructionType::Swap => {
+                let offset = index.unwrap();
+                self.require(offset + 1, address);
+                self.consume_fuel();
+                // mov rax, [top]; mov rcx, [other]; mov [top], rcx; mov [other], rax
+                self.emit(&[0x4b, 0x8b, 0x84]);
+                self.stack_operand(1);
+                self.emit(&[0x4b, 0x8b, 0x8c]);
+                self.stack_operand(offset + 1);
+                self.emit(&[0x4b, 0x89, 0x8c]);
+                self.stack_operand(1);
+                self.emit(&[0x4b, 0x89, 0x84]);
+                self.stack_operand(offset + 1);
+            }
+            InstructionType::Add
+            | InstructionType::Subtract
+            | InstructionType::Multiply
+            | InstructionType::Divide => {
+                self.require(2, address);
+                if instruction.instruction_type == InstructionType::Divide {
+                    // movsd xmm1, [top]; xorpd xmm2, xmm2; ucomisd xmm1, xmm2
+                    self.emit(&[0xf2, 0x43, 0x0f, 0x10, 0x8c]);
+                    self.stack_operand(1);
+                    self.emit(&[0x66, 0x0f, 0x57, 0xd2]);
+                    self.emit(&[0x66, 0x0f, 0x2e, 0xca]);
+                    // nan is not zero: jp over the je to slow.
+                    self.emit(&[0x7a, 0x06]);
+                    self.slow_if(0x84, address);
+                }
+                self.consume_fuel();
+                let operation = match instruction.instruction_type {
+                    InstructionType::Add => 0x58,
+                    InstructionType::Subtract => 0x5c,
+                    InstructionType::Multiply => 0x59,
+                    _ => 0x5e,
+                };
+                // movsd xmm0, [second]; op xmm0, [top]; movsd [second], xmm0; dec r13
+                self.emit(&[0xf2, 0x43, 0x0f, 0x10, 0x84]);
+                self.stack_operand(2);
+                self.emit(&[0xf2, 0x43, 0x0f, operation, 0x84]);
+                self.stack_operand(1);
+                self.emit(&[0xf2, 0x43, 0x0f, 0x11, 0x84]);
+                self.stack_operand(2);
+                self.emit(&[0x49, 0xff, 0xcd]);
+            }
+            InstructionType::Equal | InstructionType::GreaterEqual => {
+                self.require(2, address);
+                self.consume_fuel();
+                // movsd xmm0, [second]; movsd xmm1, [top]
+                self.emit(&[0xf2, 0x43, 0x0f, 0x10, 0x84]);
+                self.stack_operand(2);
+                self.emit(&[0xf2, 0x43, 0x0f, 0x10, 0x8c]);
+                self.stack_operand(1);
+                // both comparisons are false for nan, like rust's.
+                if instruction.instruction_type == InstructionType::Equal {
+                    // cmpeqsd xmm1, xmm0
+                    self.emit(&[0xf2, 0x0f, 0xc2, 0xc8, 0x00]);
+                } else {
+                    // cmplesd xmm1, xmm0: b <= a
+                    self.emit(&[0xf2, 0x0f, 0xc2, 0xc8, 0x02]);
+                }
+                self.one_or_zero();
+                self.emit(&[0xf2, 0x43, 0x0f, 0x11, 0x8c]);
+                self.stack_operand(2);
+                self.emit(&[0x49, 0xff, 0xcd]);
+            }
+            InstructionType::Not => {
+                self.require(1, address);
+                self.consume_fuel();
+                // movsd xmm1, [top]; xorpd xmm0, xmm0; cmpeqsd xmm1, xmm0
+                self.emit(&[0xf2, 0x43, 0x0f, 0x10, 0x8c]);
+                self.stack_operand(1);
+                self.emit(&[0x66, 0x0f, 0x57, 0xc0]);
+                self.emit(&[0xf2, 0x0f, 0xc2, 0xc8, 0x00]);
+                self.one_or_zero();
+                self.emit(&[0xf2, 0x43, 0x0f, 0x11, 0x8c]);
+                self.stack_operand(1);
+            }
+            InstructionType::Jump => {
+                self.consume_fuel();
+                self.jump(Label::Instruction(index.unwrap()));
+            }
+            InstructionType::JumpIf => {
+                self.require(1, address);
+                self.consume_fuel();
+                // movsd xmm1, [top]; xorpd xmm2, xmm2; ucomisd xmm1, xmm2
+                self.emit(&[0xf2, 0x43, 0x0f, 0x10, 0x8c]);
+                self.stack_operand(1);
+                self.emit(&[0x66, 0x0f, 0x57, 0xd2]);
+                self.emit(&[0x66, 0x0f, 0x2e, 0xca]);
+                // anything but zero jumps, nan included.
+                let target = Label::Instruction(index.unwrap());
+                self.jump_if(0x8a, target);
+                self.jump_if(0x85, target);
+            }
+            _ => unreachable!(),
+        }
+        true
+    }
+
+    // leaves the instruction to the callback (which traps) with fewer than `count` values.
+    fn require(&mut self, count: usize, address: usize) {
+        // cmp r13, count; jb slow
+        self.emit(&[0x49, 0x81, 0xfd]);
+        self.emit(&(count as u32).to_le_bytes());
+        self.slow_if(0x82, address);
+    }
+
+    fn consume_fuel(&mut self) {
+        // dec r15
+        self.emit(&[0x49, 0xff, 0xcf]);
+    }
+
+    // turns the all-ones or all-zeros mask in xmm1 into 1.0 or 0.0.
+    fn one_or_zero(&mut self) {
+        // mov rax, 1.0; movq xmm2, rax; andpd xmm1, xmm2
+        self.emit(&[0x48, 0xb8]);
+        self.emit(&ONE.to_le_bytes());
+        self.emit(&[0x66, 0x48, 0x0f, 0x6e, 0xd0]);
+        self.emit(&[0x66, 0x0f, 0x54, 0xca]);
+    }
+
+    // the bytes, the table of where each instruction starts (its position and the offsets)
+    // and where the code is entered.
+    fn finish(mut self) -> (Vec<u8>, (usize, Vec<usize>), usize) {
+        // entered as fn(machine: rdi, address: rsi) with the system v calling convention.
+        let entry = self.code.len();
+        // push rbx, rbp, r12, r13, r14, r15; sub rsp, 8 (aligning the stack for calls)
+        self.emit(&[0x53, 0x55, 0x41, 0x54, 0x41, 0x55, 0x41, 0x56, 0x41, 0x57]);
+        self.emit(&[0x48, 0x83, 0xec, 0x08]);
+        // mov rbx, rdi
+        self.emit(&[0x48, 0x89, 0xfb]);
+        self.load_registers();
+        // mov rax, rsi
+        self.emit(&[0x48, 0x89, 0xf0]);
+        self.dispatch();
+
+        self.label(Label::Done);
+        // add rsp, 8; pop r15, r14, r13, r12, rbp, rbx; ret
+        self.emit(&[0x48, 0x83, 0xc4, 0x08]);
+        self.emit(&[
+            0x41, 0x5f, 0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x5d, 0x5b, 0xc3,
+        ]);
+
+        while !self.code.len().is_multiple_of(8) {
+            self.code.push(0xcc);
+        }
+        self.label(Label::Table);
+        let table = self.code.len();
+        let offsets: Vec<usize> = (0..=self.length)
+            .map(|address| self.labels[&Label::Instruction(address)])
+            .collect();
+        self.code.resize(table + offsets.len() * 8, 0);
+
+        for (position, label) in &self.fixups {
+            let target = self.labels[label] as i64;
+            let relative = (target - (*position as i64 + 4)) as i32;
+            self.code[*position..*position + 4].copy_from_slice(&relative.to_le_bytes());
+        }
+        (self.code, (table, offsets), entry)
+    }
+}
@@ -0,0 +1,376 @@
+use crate::{
+    error::LexingError,
+    global::{Float, Integer},
+};
+
+// integers above this magnitude can not be stored in a `Float` operand without losing precision.
+const MAX_EXACT_INTEGER: Integer = 1 << 53;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Integer(Integer),
+    Float(Float),
+}
+
+impl Value {
+    pub fn as_float(self) -> Float {
+        match self {
+            Value::Integer(value) => value as Float,
+            Value::Float(value) => value,
+        }
+    }
+
+    pub fn to_operand(self) -> Result<Float, LexingError> {
+        match self {
+            Value::Integer(value) => {
+                if !(-MAX_EXACT_INTEGER..=MAX_EXACT_INTEGER).contains(&value) {
+                    return Err(LexingError::ExpressionOverflow);
+                }
+                Ok(value as Float)
+            }
+            Value::Float(value) => Ok(value),
+        }
+    }
+
+    pub(crate) fn as_integer(self) -> Result<Integer, LexingError> {
+        match self {
+            Value::Integer(value) => Ok(value),
+            Value::Float(_) => Err(LexingError::NonIntegerOperand),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Name(String),
+    LocalName(String),
+    NumericLabel { number: Integer, forward: bool },
+    Directive(String),
+    Number(Value),
+    String(String),
+    Colon,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+// splits one line of source into tokens. whitespace of any kind separates tokens,
+// and a `;` outside of a string or character literal starts a comment running to the end of the line.
+pub fn tokenize_line(line: &str) -> Result<Vec<Token>, LexingError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let current = chars[index];
+
+        if current.is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        if current == ';' {
+            break;
+        }
+
+        if current.is_ascii_digit() || (current == '.' && next_is_digit(&chars, index)) {
+            tokens.push(lex_number(&chars, &mut index)?);
+            continue;
+        }
+
+        if current == '@' && is_name_start(chars.get(index + 1)) {
+            index += 1;
+            tokens.push(Token::LocalName(take_identifier(&chars, &mut index)));
+            continue;
+        }
+
+        if current == '\'' {
+            tokens.push(Token::Number(lex_character(&chars, &mut index)?));
+            continue;
+        }
+
+        if current == '"' {
+            tokens.push(Token::String(lex_string(&chars, &mut index)?));
+            continue;
+        }
+
+        if current == '.' && is_name_start(chars.get(index + 1)) {
+            index += 1;
+            tokens.push(Token::Directive(take_identifier(&chars, &mut index)));
+            continue;
+        }
+
+        if is_name_start(Some(&current)) {
+            let name = take_name(&chars, &mut index);
+            match name.to_lowercase().as_str() {
+                "inf" | "infinity" => tokens.push(Token::Number(Value::Float(Float::INFINITY))),
+                "nan" => tokens.push(Token::Number(Value::Float(Float::NAN))),
+                _ => tokens.push(Token::Name(name)),
+            }
+            continue;
+        }
+
+        let token = match current {
+            ':' => Token::Colon,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '&' => Token::Ampersand,
+            '|' => Token::Pipe,
+            '^' => Token::Caret,
+            '~' => Token::Tilde,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '<' if chars.get(index + 1) == Some(&'<') => {
+                index += 1;
+                Token::ShiftLeft
+            }
+            '>' if chars.get(index + 1) == Some(&'>') => {
+                index += 1;
+                Token::ShiftRight
+            }
+            _ => return Err(LexingError::IllegalCharacter(current)),
+        };
+        index += 1;
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn is_name_start(current: Option<&char>) -> bool {
+    current
+        .map(|current| current.is_alphabetic() || *current == '_')
+        .unwrap_or(false)
+}
+
+fn take_identifier(chars: &[char], index: &mut usize) -> String {
+    let start = *index;
+    while is_name_char(chars.get(*index)) {
+        *index += 1;
+    }
+    chars[start..*index].iter().collect()
+}
+
+// a name is an identifier, optionally qualified with a local label (`main@loop`).
+fn take_name(chars: &[char], index: &mut usize) -> String {
+    let mut name = take_identifier(chars, index);
+    if chars.get(*index) == Some(&'@') && is_name_start(chars.get(*index + 1)) {
+        *index += 1;
+        name.push('@');
+        name.push_str(&take_identifier(chars, index));
+    }
+    name
+}
+
+fn next_is_digit(chars: &[char], index: usize) -> bool {
+    chars
+        .get(index + 1)
+        .map(|next| next.is_ascii_digit())
+        .unwrap_or(false)
+}
+
+pub fn is_reserved_name(name: &str) -> bool {
+    matches!(name.to_lowercase().as_str(), "inf" | "infinity" | "nan")
+}
+
+// lexes decimal, hex (0x), binary (0b) and octal (0o) literals with `_` separators,
+// followed by an optional `i64` (integer) or `f64` (float) suffix.
+// a decimal integer followed by `f` or `b` is a reference to a numeric label instead,
+// so `0b` is only binary when a digit follows.
+fn lex_number(chars: &[char], index: &mut usize) -> Result<Token, LexingError> {
+    let radix = match (chars[*index], chars.get(*index + 1)) {
+        ('0', Some('x' | 'X')) => 16,
+        ('0', Some('b' | 'B'))
+            if chars
+                .get(*index + 2)
+                .is_some_and(|next| next.is_digit(2) || *next == '_') =>
+        {
+            2
+        }
+        ('0', Some('o' | 'O')) => 8,
+        _ => 10,
+    };
+
+    let value = if radix != 10 {
+        *index += 2;
+        let digits = take_digits(chars, index, radix);
+        if digits.is_empty() {
+            return Err(LexingError::IllegalLiteral);
+        }
+        Value::Integer(
+            Integer::from_str_radix(&digits, radix).map_err(|_| LexingError::ExpressionOverflow)?,
+        )
+    } else {
+        let mut literal = take_digits(chars, index, 10);
+        let mut is_float = false;
+        if chars.get(*index) == Some(&'.') {
+            is_float = true;
+            *index += 1;
+            literal.push('.');
+            literal.push_str(&take_digits(chars, index, 10));
+        }
+        if let Some('e' | 'E') = chars.get(*index) {
+            let mut exponent_end = *index + 1;
+            let mut exponent = String::from("e");
+            if let Some(sign @ ('+' | '-')) = chars.get(exponent_end) {
+                exponent.push(*sign);
+                exponent_end += 1;
+            }
+            if next_is_digit(chars, exponent_end - 1) {
+                is_float = true;
+                *index = exponent_end;
+                literal.push_str(&exponent);
+                literal.push_str(&take_digits(chars, index, 10));
+            }
+        }
+
+        match literal.parse() {
+            Ok(value) if !is_float => Value::Integer(value),
+            // too large for an integer, which is what a float literal is for.
+            _ => Value::Float(literal.parse().map_err(|_| LexingError::IllegalLiteral)?),
+        }
+    };
+
+    let suffix = take_identifier(chars, index);
+    match (suffix.as_str(), value) {
+        // a plain literal past what a float holds exactly is read as the nearest float.
+        ("", Value::Integer(value))
+            if value.unsigned_abs() > MAX_EXACT_INTEGER as u64 && radix == 10 =>
+        {
+            Ok(Token::Number(Value::Float(value as Float)))
+        }
+        ("", value) => Ok(Token::Number(value)),
+        ("f64", value) => Ok(Token::Number(Value::Float(value.as_float()))),
+        ("i64", Value::Float(value)) => {
+            if value.fract() != 0. || !value.is_finite() {
+                return Err(LexingError::NonIntegerOperand);
+            }
+            if value.abs() >= Integer::MAX as Float {
+                return Err(LexingError::ExpressionOverflow);
+            }
+            Ok(Token::Number(Value::Integer(value as Integer)))
+        }
+        ("i64", value) => Ok(Token::Number(value)),
+        ("f" | "b", Value::Integer(number)) if radix == 10 => Ok(Token::NumericLabel {
+            number,
+            forward: suffix == "f",
+        }),
+        _ => Err(LexingError::IllegalLiteral),
+    }
+}
+
+fn take_digits(chars: &[char], index: &mut usize, radix: u32) -> String {
+    let mut digits = String::new();
+    while let Some(current) = chars.get(*index) {
+        if current.is_digit(radix) {
+            digits.push(*current);
+        } else if *current != '_' {
+            break;
+        }
+        *index += 1;
+    }
+    digits
+}
+
+fn is_name_char(current: Option<&char>) -> bool {
+    current
+        .map(|current| current.is_alphanumeric() || *current == '_')
+        .unwrap_or(false)
+}
+
+// lexes a character literal such as 'A', '\n' or '\x41' into its character code.
+fn lex_character(chars: &[char], index: &mut usize) -> Result<Value, LexingError> {
+    *index += 1;
+    let character = match chars.get(*index) {
+        Some('\\') => {
+            *index += 1;
+            lex_escape(chars, index)?
+        }
+        Some('\'') | None => return Err(LexingError::IllegalLiteral),
+        Some(character) => {
+            *index += 1;
+            *character
+        }
+    };
+    if chars.get(*index) != Some(&'\'') {
+        return Err(LexingError::IllegalLiteral);
+    }
+    *index += 1;
+    Ok(Value::Integer(character as Integer))
+}
+
+fn lex_string(chars: &[char], index: &mut usize) -> Result<String, LexingError> {
+    *index += 1;
+    let mut string = String::new();
+    loop {
+        match chars.get(*index) {
+            Some('"') => {
+                *index += 1;
+                return Ok(string);
+            }
+            Some('\\') => {
+                *index += 1;
+                string.push(lex_escape(chars, index)?);
+            }
+            Some(character) => {
+                *index += 1;
+                string.push(*character);
+            }
+            None => return Err(LexingError::IllegalLiteral),
+        }
+    }
+}
+
+fn lex_escape(chars: &[char], index: &mut usize) -> Result<char, LexingError> {
+    let escape = chars.get(*index).ok_or(LexingError::IllegalLiteral)?;
+    *index += 1;
+    match escape {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '0' => Ok('\0'),
+        '\\' => Ok('\\'),
+        '\'' => Ok('\''),
+        '"' => Ok('"'),
+        'x' => {
+            let digits: String = chars
+                .get(*index..*index + 2)
+                .ok_or(LexingError::IllegalLiteral)?
+                .iter()
+                .collect();
+            *index += 2;
+            let code = u8::from_str_radix(&digits, 16).map_err(|_| LexingError::IllegalLiteral)?;
+            Ok(code as char)
+        }
+        'u' => {
+            if chars.get(*index) != Some(&'{') {
+                return Err(LexingError::IllegalLiteral);
+            }
+            let end = chars[*index..]
+                .iter()
+                .position(|current| *current == '}')
+                .ok_or(LexingError::IllegalLiteral)?
+                + *index;
+            let digits: String = chars[*index + 1..end].iter().collect();
+            *index = end + 1;
+            let code = u32::from_str_radix(&digits, 16).map_err(|_| LexingError::IllegalLiteral)?;
+            char::from_u32(code).ok_or(LexingError::IllegalLiteral)
+        }
+        _ => Err(LexingError::IllegalLiteral),
+    }
+}
@@ -1,5 +1,19 @@
+pub mod bytecode;
+pub mod compile;
 pub mod core;
-mod error;
+pub mod error;
+mod expression;
+pub mod ffi;
 mod global;
-mod instruction;
-mod label;
+pub mod golden;
+pub mod instruction;
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+mod jit;
+pub mod lexer;
+pub mod limits;
+pub mod native;
+pub mod numeric;
+pub mod register;
+pub mod repl;
+pub mod symbol;
+pub mod trap;
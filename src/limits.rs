@@ -0,0 +1,33 @@
+use std::fmt;
+
+// caps on what a program may use while it runs. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VmLimits {
+    // values on the stack, including the locals of frames. every open frame and pending `try`
+    // handler counts as one more value.
+    pub max_stack_depth: Option<usize>,
+    // nested calls that have not returned yet.
+    pub max_call_depth: Option<usize>,
+    // bytes placed in memory by the data section. memory does not grow while the program runs,
+    // so this is checked when the program is loaded, which is why the limits are set first.
+    pub max_memory_bytes: Option<usize>,
+    // instructions executed, counting ones that trapped.
+    pub max_instructions: Option<u64>,
+    // bytes written by the output instructions.
+    pub max_output_bytes: Option<usize>,
+}
+
+// the limit a `LimitExceeded` trap ran into. running out of stack is a `StackOverflow` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    CallDepth,
+    Memory,
+    Instructions,
+    Output,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
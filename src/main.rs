@@ -1,26 +1,477 @@
-mod error;
-mod global;
-mod instruction;
-mod uvm;
+use std::{
+    collections::HashSet,
+    env::args,
+    fs,
+    io::{stderr, stdin, stdout, BufRead, Write},
+    path::Path,
+    process::exit,
+};
 
-use std::{env::args, process::exit};
+use uvm::{
+    bytecode::is_bytecode,
+    compile::to_c,
+    core::{Engine, RunStatus, UVM},
+    golden::run_directory_with,
+    limits::VmLimits,
+    numeric::Numeric,
+    repl::Repl,
+    symbol::SymbolKind,
+};
 
-use uvm::UVM;
+// exit statuses for failures, following sysexits.h.
+const EXIT_USAGE: i32 = 64;
+const EXIT_ASSEMBLE: i32 = 65;
+const EXIT_IO: i32 = 66;
+const EXIT_RUNTIME: i32 = 70;
 
-fn main() {
-    let args: Vec<String> = args().collect();
-    if args.len() == 2 {
-        let mut vm = UVM::new();
-        vm.run(&args[1]);
-    } else {
-        eprintln!(
-            "
+const USAGE: &str = "
 Program: UVM
 
 Usage:
-    <source_path>: executes the (given) file.
-        "
-        );
-        exit(1);
+    uvm run <path>                  executes the (given) file.
+    uvm emulate --limit <n> <path>  executes at most n instructions of the file.
+    uvm check <path>                assembles the file and reports errors and warnings.
+    uvm asm <path> [-o <output>]    assembles the file into bytecode (<path>.uvmb by default).
+    uvm disasm <path>               prints the (assembled) file as assembly.
+    uvm compile --to c <path> [-o <output>]
+                                    translates the file into a c program (<path>.c by default),
+                                    built with e.g. `cc program.c -lm`.
+    uvm trace <path>                executes the file, printing every instruction and the stack.
+    uvm debug <path>                executes the file step by step.
+    uvm repl                        executes instructions as they are typed in.
+    uvm test <directory>            runs the golden programs (*.uasm) in the directory.
+
+Options:
+    --stack-limit <n>  traps with StackOverflow when the stack grows past n values.
+    --quiet, -q        does not print warnings.
+    --integer          does integer arithmetic within ±2^53 (not the full i64 range),
+                       trapping with IntegerOverflow past it.
+    --jit              compiles the program to native code first (when uvm is built with the
+                       `jit` feature on x86-64 linux, otherwise the interpreter runs it).
+    --registers        translates the program into register operations first.
+
+Files starting with the bytecode header are loaded as bytecode, *.uvmn files as numeric
+opcodes (a line per instruction, e.g. `0 2` for `push 2`) and anything else as assembly.
+Exits with the code given to `hlt`, 65 when the file does not assemble and 70 when it traps.
+";
+
+struct Options {
+    command: String,
+    path: String,
+    output: Option<String>,
+    limit: Option<u64>,
+    target: Option<String>,
+    stack_limit: Option<usize>,
+    quiet: bool,
+    integer: bool,
+    jit: bool,
+    registers: bool,
+}
+
+fn main() {
+    let options = match parse_options(args().skip(1).collect()) {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            exit(EXIT_USAGE);
+        }
+    };
+
+    if options.command == "repl" {
+        exit(repl());
+    }
+    if options.command == "test" {
+        exit(test(&options.path, engine(&options)));
+    }
+
+    let mut vm = match load(&options) {
+        Ok(vm) => vm,
+        Err(code) => exit(code),
+    };
+    let code = match options.command.as_str() {
+        "run" => run(&mut vm),
+        "emulate" => emulate(&mut vm, options.limit.unwrap_or(u64::MAX)),
+        "check" => 0,
+        "asm" => assemble(&vm, &options),
+        "compile" => compile(&vm, &options),
+        "disasm" => {
+            print!("{}", vm.disassemble());
+            0
+        }
+        "trace" => trace(&mut vm),
+        "debug" => debug(&mut vm),
+        _ => unreachable!(),
+    };
+    exit(code);
+}
+
+fn parse_options(args: Vec<String>) -> Option<Options> {
+    let mut args = args.into_iter();
+    let command = args.next()?;
+    if !matches!(
+        command.as_str(),
+        "run"
+            | "emulate"
+            | "check"
+            | "asm"
+            | "disasm"
+            | "compile"
+            | "trace"
+            | "debug"
+            | "repl"
+            | "test"
+    ) {
+        return None;
+    }
+
+    let mut options = Options {
+        command,
+        path: String::new(),
+        output: None,
+        limit: None,
+        target: None,
+        stack_limit: None,
+        quiet: false,
+        integer: false,
+        jit: false,
+        registers: false,
+    };
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => options.limit = Some(args.next()?.parse().ok()?),
+            "--to" => options.target = Some(args.next()?),
+            "--stack-limit" => options.stack_limit = Some(args.next()?.parse().ok()?),
+            "--quiet" | "-q" => options.quiet = true,
+            "--integer" => options.integer = true,
+            "--jit" => options.jit = true,
+            "--registers" => options.registers = true,
+            "-o" | "--output" => options.output = Some(args.next()?),
+            _ if arg.starts_with('-') || path.is_some() => return None,
+            _ => path = Some(arg),
+        }
+    }
+
+    if options.command == "emulate" && options.limit.is_none() {
+        return None;
+    }
+    // one engine at a time.
+    if options.jit && options.registers {
+        return None;
+    }
+    // c is the only target so far.
+    if options.command == "compile" && options.target.as_deref() != Some("c") {
+        return None;
+    }
+    match (options.command.as_str(), path) {
+        ("repl", None) => {}
+        ("repl", Some(_)) | (_, None) => return None,
+        (_, Some(path)) => options.path = path,
+    }
+    Some(options)
+}
+
+fn load(options: &Options) -> Result<UVM, i32> {
+    let mut vm = UVM::new();
+    vm.set_limits(VmLimits {
+        max_stack_depth: options.stack_limit,
+        ..VmLimits::default()
+    });
+    if options.integer {
+        vm.set_numeric(Numeric::Integer);
+    }
+    vm.set_engine(engine(options));
+
+    let bytes = fs::read(&options.path).map_err(|err| {
+        eprintln!("ERROR: {}: {}", options.path, err);
+        EXIT_IO
+    })?;
+    if is_bytecode(&bytes) {
+        if let Some(err) = vm.load_bytecode(&bytes) {
+            eprintln!("BytecodeError: {}", err);
+            return Err(EXIT_ASSEMBLE);
+        }
+        return Ok(vm);
+    }
+
+    let source = String::from_utf8(bytes).map_err(|_| {
+        eprintln!("ERROR: {}: not valid utf-8", options.path);
+        EXIT_IO
+    })?;
+    let is_numeric = Path::new(&options.path)
+        .extension()
+        .is_some_and(|extension| extension == "uvmn");
+    let errors = if is_numeric {
+        vm.load_numeric(&source)
+    } else {
+        vm.load_program(&source)
+    };
+    if !options.quiet {
+        for warning in vm.warnings() {
+            eprintln!("LexingWarning: {}", warning);
+        }
+    }
+    if let Some(errors) = errors {
+        for err in errors {
+            eprintln!("LexingError: {}", err);
+        }
+        return Err(EXIT_ASSEMBLE);
+    }
+    Ok(vm)
+}
+
+fn engine(options: &Options) -> Engine {
+    if options.jit {
+        Engine::Jit
+    } else if options.registers {
+        Engine::Register
+    } else {
+        Engine::Interpreter
+    }
+}
+
+fn run(vm: &mut UVM) -> i32 {
+    match vm.execute() {
+        Ok(RunStatus::Halted(code)) => code as i32,
+        Ok(_) => 0,
+        Err(context) => {
+            eprintln!("Trap: {}", context);
+            EXIT_RUNTIME
+        }
+    }
+}
+
+fn emulate(vm: &mut UVM, limit: u64) -> i32 {
+    match vm.execute_for(limit) {
+        Ok(RunStatus::Paused) => {
+            eprintln!("stopped after {} instructions", limit);
+            0
+        }
+        Ok(RunStatus::Halted(code)) => code as i32,
+        Ok(_) => 0,
+        Err(context) => {
+            eprintln!("Trap: {}", context);
+            EXIT_RUNTIME
+        }
+    }
+}
+
+fn assemble(vm: &UVM, options: &Options) -> i32 {
+    let output = match &options.output {
+        Some(output) => output.to_string(),
+        None => Path::new(&options.path)
+            .with_extension("uvmb")
+            .to_string_lossy()
+            .to_string(),
+    };
+    if let Err(err) = fs::write(&output, vm.to_bytecode()) {
+        eprintln!("ERROR: {}: {}", output, err);
+        return EXIT_IO;
+    }
+    0
+}
+
+fn compile(vm: &UVM, options: &Options) -> i32 {
+    let c = match to_c(vm) {
+        Ok(c) => c,
+        Err(err) => {
+            eprintln!("CompileError: {}", err);
+            return EXIT_ASSEMBLE;
+        }
+    };
+    let output = match &options.output {
+        Some(output) => output.to_string(),
+        None => Path::new(&options.path)
+            .with_extension("c")
+            .to_string_lossy()
+            .to_string(),
+    };
+    if let Err(err) = fs::write(&output, c) {
+        eprintln!("ERROR: {}: {}", output, err);
+        return EXIT_IO;
+    }
+    0
+}
+
+// prints every instruction before it executes and the stack after it, on stderr so the
+// program's own output stays apart.
+fn trace(vm: &mut UVM) -> i32 {
+    while !vm.is_halted() {
+        let address = vm.instruction_pointer();
+        let instruction = match vm.program().get(address) {
+            Some(instruction) => instruction.to_string(),
+            None => String::from("?"),
+        };
+        if let Some(context) = vm.step() {
+            eprintln!("Trap: {}", context);
+            return EXIT_RUNTIME;
+        }
+        eprintln!("{:>5}  {:<20} {:?}", address, instruction, vm.stack());
+    }
+    vm.exit_code() as i32
+}
+
+fn test(directory: &str, engine: Engine) -> i32 {
+    let report = match run_directory_with(Path::new(directory), engine) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("ERROR: {}: {}", directory, err);
+            return EXIT_IO;
+        }
+    };
+
+    for path in &report.passed {
+        println!("ok      {}", path.display());
+    }
+    for failure in &report.failures {
+        println!("FAILED  {}", failure.path.display());
+        for line in failure.message.lines() {
+            println!("    {}", line);
+        }
+    }
+    println!(
+        "{} passed, {} failed",
+        report.passed.len(),
+        report.failures.len()
+    );
+    if report.failures.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+fn repl() -> i32 {
+    let mut repl = Repl::new();
+    eprintln!("uvm repl, :help for help");
+    let mut lines = stdin().lock().lines();
+    while !repl.is_finished() {
+        eprint!("{}", repl.prompt());
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let response = repl.feed(&line);
+        if !response.is_empty() {
+            eprintln!("{}", response);
+        }
+    }
+    0
+}
+
+const DEBUG_HELP: &str = "commands:
+    s, step [n]          executes n (default 1) instructions
+    c, continue          executes until a breakpoint, a trap or the end
+    b, break <at>        sets a breakpoint at an instruction index or code label
+    d, delete <at>       removes a breakpoint
+    p, stack             prints the stack and frames
+    l, list              prints the instructions around the current one
+    q, quit              stops debugging";
+
+fn debug(vm: &mut UVM) -> i32 {
+    let mut breakpoints: HashSet<usize> = HashSet::new();
+    eprintln!("{}", DEBUG_HELP);
+    show_current(vm);
+
+    let mut lines = stdin().lock().lines();
+    loop {
+        // the program's output so far comes before the prompt.
+        stdout().flush().ok();
+        eprint!("(uvm) ");
+        stderr().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return 0,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            [] => {}
+            ["s" | "step", rest @ ..] => {
+                let count = match rest.first().map(|count| count.parse::<usize>()) {
+                    Some(Ok(count)) => count,
+                    Some(Err(_)) => {
+                        eprintln!("not a number");
+                        continue;
+                    }
+                    None => 1,
+                };
+                for _ in 0..count {
+                    if !debug_step(vm) {
+                        break;
+                    }
+                }
+                show_current(vm);
+            }
+            ["c" | "continue"] => {
+                while debug_step(vm) {
+                    if breakpoints.contains(&vm.instruction_pointer()) {
+                        eprintln!("breakpoint");
+                        break;
+                    }
+                }
+                show_current(vm);
+            }
+            ["b" | "break", at] => match resolve_address(vm, at) {
+                Some(address) => {
+                    breakpoints.insert(address);
+                }
+                None => eprintln!("no such instruction or code label"),
+            },
+            ["d" | "delete", at] => match resolve_address(vm, at) {
+                Some(address) => {
+                    breakpoints.remove(&address);
+                }
+                None => eprintln!("no such instruction or code label"),
+            },
+            ["p" | "stack"] => {
+                eprintln!("stack: {:?}", vm.stack());
+                if !vm.frames().is_empty() {
+                    eprintln!("{}", vm.format_frames());
+                }
+            }
+            ["l" | "list"] => {
+                let current = vm.instruction_pointer();
+                for address in current.saturating_sub(3)..(current + 4).min(vm.program().len()) {
+                    let marker = if address == current { "=>" } else { "  " };
+                    eprintln!("{} {:>5}  {}", marker, address, vm.program()[address]);
+                }
+            }
+            ["q" | "quit"] => return 0,
+            _ => eprintln!("{}", DEBUG_HELP),
+        }
+    }
+}
+
+// executes one instruction, returning false once the program can not go on.
+fn debug_step(vm: &mut UVM) -> bool {
+    if vm.is_halted() {
+        eprintln!("halted");
+        return false;
+    }
+    if let Some(context) = vm.step() {
+        eprintln!("Trap: {}", context);
+        return false;
+    }
+    true
+}
+
+fn show_current(vm: &UVM) {
+    match vm.program().get(vm.instruction_pointer()) {
+        Some(instruction) if !vm.is_halted() => {
+            eprintln!("=> {:>5}  {}", vm.instruction_pointer(), instruction)
+        }
+        _ => {}
+    }
+}
+
+fn resolve_address(vm: &UVM, at: &str) -> Option<usize> {
+    if let Ok(address) = at.parse::<usize>() {
+        return (address < vm.program().len()).then_some(address);
+    }
+    let symbol = vm.symbols().get(at)?;
+    if symbol.kind != SymbolKind::CodeLabel {
+        return None;
     }
+    symbol.address()
 }
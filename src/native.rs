@@ -0,0 +1,55 @@
+use crate::global::Float;
+
+// a host function callable from uvm programs. it receives its arguments in push order
+// and returns its results in the order they should be pushed, or a message describing the failure.
+pub type NativeFunction = Box<dyn FnMut(&[Float]) -> Result<Vec<Float>, String>>;
+
+pub struct Native {
+    pub name: String,
+    pub arity: usize,
+    pub returns: usize,
+    pub function: NativeFunction,
+}
+
+#[derive(Default)]
+pub struct NativeRegistry {
+    natives: Vec<Native>,
+}
+
+impl NativeRegistry {
+    pub fn new() -> Self {
+        Self {
+            natives: Vec::new(),
+        }
+    }
+
+    // registering a name again replaces the earlier native but keeps its id.
+    pub fn register(&mut self, native: Native) -> usize {
+        if let Some(id) = self.find(&native.name) {
+            self.natives[id] = native;
+            return id;
+        }
+        self.natives.push(native);
+        self.natives.len() - 1
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.natives.iter().position(|native| native.name == name)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Native> {
+        self.natives.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Native> {
+        self.natives.get_mut(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.natives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.natives.is_empty()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    global::{Float, Integer},
+    trap::Trap,
+};
+
+// from here on not every integer is a float.
+const MAX_EXACT_INTEGER: Integer = 1 << 53;
+
+// how the vm does arithmetic. values are always stored as floats: the float backend uses
+// them as they are, the integer backend keeps them whole and within ±2^53 (where integers
+// and floats agree), divides without a fraction and traps instead of losing precision.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Numeric {
+    #[default]
+    Float,
+    // not i64 arithmetic: the stack still holds floats, so the range is ±2^53 rather than
+    // ±2^63, and anything past it traps with `IntegerOverflow` instead of wrapping.
+    Integer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Numeric {
+    // a value pushed by the program.
+    pub fn value(self, value: Float) -> Result<Float, Trap> {
+        match self {
+            Numeric::Float => Ok(value),
+            Numeric::Integer => to_integer(value).map(|value| value as Float),
+        }
+    }
+
+    // division by zero is checked by the caller, so the operands stay on the stack for a
+    // trap handler.
+    pub fn apply(self, operation: Operation, a: Float, b: Float) -> Result<Float, Trap> {
+        match self {
+            Numeric::Float => Ok(match operation {
+                Operation::Add => a + b,
+                Operation::Subtract => a - b,
+                Operation::Multiply => a * b,
+                Operation::Divide => a / b,
+            }),
+            Numeric::Integer => {
+                let a = to_integer(a)?;
+                let b = to_integer(b)?;
+                let result = match operation {
+                    Operation::Add => a.checked_add(b),
+                    Operation::Subtract => a.checked_sub(b),
+                    Operation::Multiply => a.checked_mul(b),
+                    Operation::Divide => a.checked_div(b),
+                };
+                match result {
+                    Some(result) if result.abs() <= MAX_EXACT_INTEGER => Ok(result as Float),
+                    _ => Err(Trap::IntegerOverflow),
+                }
+            }
+        }
+    }
+}
+
+fn to_integer(value: Float) -> Result<Integer, Trap> {
+    if value.fract() != 0. || !value.is_finite() {
+        return Err(Trap::IllegalOperand);
+    }
+    if value.abs() > MAX_EXACT_INTEGER as Float {
+        return Err(Trap::IntegerOverflow);
+    }
+    Ok(value as Integer)
+}
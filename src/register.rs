@@ -0,0 +1,516 @@
+// translates a program for the register engine. every run of instructions that only moves and
+// computes values (a block) becomes three-address operations: the stack slots the block reads
+// are registers, pushes, pops, `dup`s and `swp`s only change which register a slot stands for,
+// and the stack is written once, when the block is left. blocks only run when none of their
+// instructions can trap or run into a limit; everything else is left to the interpreter.
+use std::fmt;
+
+use crate::{
+    core::index_operand,
+    global::{Float, Integer},
+    instruction::{identical, Instruction, InstructionType},
+    numeric::Numeric,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    // the stack value this many places above the block's base, as it was when it was entered.
+    Slot(usize),
+    Register(usize),
+    Constant(Float),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Binary {
+        operator: Operator,
+        to: usize,
+        a: Operand,
+        b: Operand,
+    },
+    Not {
+        to: usize,
+        a: Operand,
+    },
+    Move {
+        to: usize,
+        from: Operand,
+    },
+    // the stack gets this many values above the base.
+    Resize(usize),
+    Store {
+        slot: usize,
+        from: Operand,
+    },
+}
+
+// where to continue once a block is done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Goto(usize),
+    // `jmpif`, which leaves the condition on the stack.
+    Branch { target: usize, next: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub start: usize,
+    // the instructions the block stands for.
+    pub length: u64,
+    // how many values the block takes from the stack, which is where its base is.
+    pub needs: usize,
+    // the most the stack grows beyond its length on entry (before any instruction, as the
+    // stack limit is checked).
+    pub peak: isize,
+    // a block starting with `div` only runs when the divisor is not 0.
+    pub checks_divisor: bool,
+    pub ops: Vec<Op>,
+    pub exit: Exit,
+}
+
+pub struct Translation {
+    blocks: Vec<Block>,
+    // the block starting at each instruction, if one does.
+    starts: Vec<Option<usize>>,
+    registers: usize,
+    // what was translated, so the translation is only reused for the same program.
+    program: Vec<Instruction>,
+    numeric: Numeric,
+}
+
+impl Operator {
+    pub fn apply(self, a: Float, b: Float) -> Float {
+        match self {
+            Operator::Add => a + b,
+            Operator::Subtract => a - b,
+            Operator::Multiply => a * b,
+            Operator::Divide => a / b,
+            Operator::Equal => ((a == b) as Integer) as Float,
+            Operator::GreaterEqual => ((a >= b) as Integer) as Float,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Equal => "==",
+            Operator::GreaterEqual => ">=",
+        }
+    }
+
+    fn from_instruction(instruction_type: InstructionType) -> Option<Self> {
+        match instruction_type {
+            InstructionType::Add => Some(Operator::Add),
+            InstructionType::Subtract => Some(Operator::Subtract),
+            InstructionType::Multiply => Some(Operator::Multiply),
+            InstructionType::Divide => Some(Operator::Divide),
+            InstructionType::Equal => Some(Operator::Equal),
+            InstructionType::GreaterEqual => Some(Operator::GreaterEqual),
+            _ => None,
+        }
+    }
+}
+
+fn not(a: Float) -> Float {
+    (!(a != 0.) as Integer) as Float
+}
+
+impl Block {
+    // runs the block on a stack with at least `needs` values and returns where to continue.
+    pub fn run(&self, stack: &mut Vec<Float>, registers: &mut [Float]) -> usize {
+        let base = stack.len() - self.needs;
+        for op in &self.ops {
+            match *op {
+                Op::Binary { operator, to, a, b } => {
+                    let a = read(a, stack, base, registers);
+                    let b = read(b, stack, base, registers);
+                    registers[to] = operator.apply(a, b);
+                }
+                Op::Not { to, a } => registers[to] = not(read(a, stack, base, registers)),
+                Op::Move { to, from } => registers[to] = read(from, stack, base, registers),
+                Op::Resize(length) => stack.resize(base + length, 0.),
+                Op::Store { slot, from } => stack[base + slot] = read(from, stack, base, registers),
+            }
+        }
+        match self.exit {
+            Exit::Goto(address) => address,
+            Exit::Branch { target, next } => {
+                if stack[stack.len() - 1] != 0. {
+                    target
+                } else {
+                    next
+                }
+            }
+        }
+    }
+}
+
+fn read(operand: Operand, stack: &[Float], base: usize, registers: &[Float]) -> Float {
+    match operand {
+        Operand::Slot(slot) => stack[base + slot],
+        Operand::Register(register) => registers[register],
+        Operand::Constant(value) => value,
+    }
+}
+
+impl Translation {
+    // None for integer arithmetic, which can trap on every instruction.
+    pub fn new(program: &[Instruction], numeric: Numeric) -> Option<Self> {
+        if numeric != Numeric::Float {
+            return None;
+        }
+
+        let leaders = leaders(program);
+        let mut translation = Self {
+            blocks: Vec::new(),
+            starts: vec![None; program.len()],
+            registers: 0,
+            program: program.to_vec(),
+            numeric,
+        };
+        for start in 0..program.len() {
+            if !leaders[start] {
+                continue;
+            }
+            if let Some((block, registers)) = translate_block(program, &leaders, start) {
+                translation.starts[start] = Some(translation.blocks.len());
+                translation.blocks.push(block);
+                translation.registers = translation.registers.max(registers);
+            }
+        }
+        Some(translation)
+    }
+
+    pub fn is_for(&self, program: &[Instruction], numeric: Numeric) -> bool {
+        self.numeric == numeric && identical(&self.program, program)
+    }
+
+    pub fn block(&self, address: usize) -> Option<&Block> {
+        let index = (*self.starts.get(address)?)?;
+        Some(&self.blocks[index])
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    // how many registers running the blocks takes.
+    pub fn registers(&self) -> usize {
+        self.registers
+    }
+}
+
+// an instruction a block can stand for.
+fn is_translated(instruction: &Instruction) -> bool {
+    let operand = index_operand(instruction.operand);
+    match instruction.instruction_type {
+        InstructionType::Push => instruction.operand.is_some(),
+        InstructionType::Duplicate | InstructionType::Jump | InstructionType::JumpIf => {
+            operand.is_some()
+        }
+        InstructionType::Swap => operand.is_some_and(|offset| offset > 0),
+        InstructionType::Pop | InstructionType::Not => true,
+        instruction_type => Operator::from_instruction(instruction_type).is_some(),
+    }
+}
+
+// where blocks start: where the program does, at every jump target and after every
+// instruction a block ends with or can not stand for. a `div` by anything but a constant
+// starts one too, so its divisor can be checked before the block runs.
+fn leaders(program: &[Instruction]) -> Vec<bool> {
+    let mut leaders = vec![false; program.len() + 1];
+    leaders[0] = true;
+    for (address, instruction) in program.iter().enumerate() {
+        let ends_block = match instruction.instruction_type {
+            InstructionType::Jump
+            | InstructionType::JumpIf
+            | InstructionType::Call
+            | InstructionType::Try => {
+                if let Some(target) = index_operand(instruction.operand) {
+                    if target < program.len() {
+                        leaders[target] = true;
+                    }
+                }
+                true
+            }
+            InstructionType::Return | InstructionType::Throw | InstructionType::Halt => true,
+            _ => !is_translated(instruction),
+        };
+        if ends_block {
+            leaders[address + 1] = true;
+        }
+        if instruction.instruction_type == InstructionType::Divide
+            && !(address > 0 && nonzero_push(&program[address - 1]))
+        {
+            leaders[address] = true;
+        }
+    }
+    leaders
+}
+
+fn nonzero_push(instruction: &Instruction) -> bool {
+    instruction.instruction_type == InstructionType::Push
+        && instruction.operand.is_some_and(|value| value != 0.)
+}
+
+// the values above the block's base while it is translated. entry values are numbered from the
+// top of the stack down until the block is done and its base is known.
+struct Values {
+    values: Vec<Operand>,
+    // how many values were taken from below.
+    taken: usize,
+    ops: Vec<Op>,
+    registers: usize,
+}
+
+impl Values {
+    // makes sure there are at least `count` values, taking them from the stack.
+    fn need(&mut self, count: usize) {
+        while self.values.len() < count {
+            self.values.insert(0, Operand::Slot(self.taken));
+            self.taken += 1;
+        }
+    }
+
+    fn register(&mut self) -> usize {
+        self.registers += 1;
+        self.registers - 1
+    }
+
+    fn height(&self) -> isize {
+        self.values.len() as isize - self.taken as isize
+    }
+}
+
+// the block starting at `start` and how many registers it uses. None when it would be empty.
+fn translate_block(
+    program: &[Instruction],
+    leaders: &[bool],
+    start: usize,
+) -> Option<(Block, usize)> {
+    let mut values = Values {
+        values: Vec::new(),
+        taken: 0,
+        ops: Vec::new(),
+        registers: 0,
+    };
+    let mut peak = isize::MIN;
+    let mut checks_divisor = false;
+    let mut address = start;
+
+    let exit = loop {
+        if address > start && leaders[address] {
+            break Exit::Goto(address);
+        }
+        let instruction = match program.get(address) {
+            Some(instruction) if is_translated(instruction) => *instruction,
+            _ => break Exit::Goto(address),
+        };
+        let operand = index_operand(instruction.operand);
+        let growth = match instruction.instruction_type {
+            InstructionType::Push | InstructionType::Duplicate => 1,
+            _ => 0,
+        };
+        peak = peak.max(values.height() + growth);
+
+        match instruction.instruction_type {
+            InstructionType::Push => values
+                .values
+                .push(Operand::Constant(instruction.operand.unwrap())),
+            InstructionType::Pop => {
+                values.need(1);
+                values.values.pop();
+            }
+            InstructionType::Duplicate => {
+                let offset = operand.unwrap();
+                values.need(offset + 1);
+                let value = values.values[values.values.len() - 1 - offset];
+                values.values.push(value);
+            }
+            InstructionType::Swap => {
+                let offset = operand.unwrap();
+                values.need(offset + 1);
+                let top = values.values.len() - 1;
+                values.values.swap(top, top - offset);
+            }
+            InstructionType::Not => {
+                values.need(1);
+                let a = values.values.pop().unwrap();
+                let value = match a {
+                    Operand::Constant(a) => Operand::Constant(not(a)),
+                    _ => {
+                        let to = values.register();
+                        values.ops.push(Op::Not { to, a });
+                        Operand::Register(to)
+                    }
+                };
+                values.values.push(value);
+            }
+            InstructionType::Jump => {
+                address += 1;
+                break Exit::Goto(operand.unwrap());
+            }
+            InstructionType::JumpIf => {
+                values.need(1);
+                address += 1;
+                break Exit::Branch {
+                    target: operand.unwrap(),
+                    next: address,
+                };
+            }
+            instruction_type => {
+                let operator = Operator::from_instruction(instruction_type).unwrap();
+                values.need(2);
+                let b = values.values[values.values.len() - 1];
+                if operator == Operator::Divide {
+                    // a divisor that may be 0 is only checked at the start of a block.
+                    match b {
+                        Operand::Constant(b) if b != 0. => {}
+                        _ if address == start => checks_divisor = true,
+                        _ => break Exit::Goto(address),
+                    }
+                }
+                values.values.pop();
+                let a = values.values.pop().unwrap();
+                let value = match (a, b) {
+                    (Operand::Constant(a), Operand::Constant(b)) => {
+                        Operand::Constant(operator.apply(a, b))
+                    }
+                    _ => {
+                        let to = values.register();
+                        values.ops.push(Op::Binary { operator, to, a, b });
+                        Operand::Register(to)
+                    }
+                };
+                values.values.push(value);
+            }
+        }
+        address += 1;
+    };
+    if address == start {
+        return None;
+    }
+
+    // now that the base is known, slots count up from it.
+    let taken = values.taken;
+    let rebase = |operand: Operand| match operand {
+        Operand::Slot(depth) => Operand::Slot(taken - 1 - depth),
+        operand => operand,
+    };
+    for op in &mut values.ops {
+        match op {
+            Op::Binary { a, b, .. } => {
+                *a = rebase(*a);
+                *b = rebase(*b);
+            }
+            Op::Not { a, .. } => *a = rebase(*a),
+            _ => {}
+        }
+    }
+    let finals: Vec<Operand> = values.values.iter().map(|value| rebase(*value)).collect();
+
+    // slots that are overwritten or dropped while other slots still need their value are
+    // saved in registers first.
+    let is_written = |slot: usize| slot >= finals.len() || finals[slot] != Operand::Slot(slot);
+    let mut stores = Vec::new();
+    for (slot, value) in finals.iter().enumerate() {
+        if *value == Operand::Slot(slot) {
+            continue;
+        }
+        let value = match value {
+            Operand::Slot(from) if is_written(*from) => {
+                let to = values.register();
+                values.ops.push(Op::Move {
+                    to,
+                    from: Operand::Slot(*from),
+                });
+                Operand::Register(to)
+            }
+            value => *value,
+        };
+        stores.push(Op::Store { slot, from: value });
+    }
+    if finals.len() != taken {
+        values.ops.push(Op::Resize(finals.len()));
+    }
+    values.ops.extend(stores);
+
+    Some((
+        Block {
+            start,
+            length: (address - start) as u64,
+            needs: taken,
+            peak,
+            checks_divisor,
+            ops: values.ops,
+            exit,
+        },
+        values.registers,
+    ))
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Slot(slot) => write!(f, "s{}", slot),
+            Operand::Register(register) => write!(f, "r{}", register),
+            Operand::Constant(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Binary { operator, to, a, b } => {
+                write!(f, "r{} = {} {} {}", to, a, operator.symbol(), b)
+            }
+            Op::Not { to, a } => write!(f, "r{} = !{}", to, a),
+            Op::Move { to, from } => write!(f, "r{} = {}", to, from),
+            Op::Resize(length) => write!(f, "resize {}", length),
+            Op::Store { slot, from } => write!(f, "s{} = {}", slot, from),
+        }
+    }
+}
+
+impl fmt::Display for Translation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for block in &self.blocks {
+            writeln!(
+                f,
+                "{}..{} (needs {}{}):",
+                block.start,
+                block.start + block.length as usize,
+                block.needs,
+                if block.checks_divisor {
+                    ", divisor checked"
+                } else {
+                    ""
+                }
+            )?;
+            for op in &block.ops {
+                writeln!(f, "    {}", op)?;
+            }
+            match block.exit {
+                Exit::Goto(address) => writeln!(f, "    goto {}", address)?,
+                Exit::Branch { target, next } => {
+                    writeln!(f, "    goto {} if the top is not 0, else {}", target, next)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs::read_to_string,
+    io::{sink, Write},
+};
+
+use crate::{core::UVM, symbol::SymbolKind};
+
+// how many instructions a single line may execute before the repl gives up on it.
+const STEP_LIMIT: usize = 10_000_000;
+
+const HELP: &str = "instructions are executed as soon as they are entered.
+a line ending with `:` (a label) or `.data` starts a block, which is assembled but not
+executed; an empty line ends the block. other directives (e.g. `.const`) are assembled
+right away.
+
+commands:
+    :load <path>  assembles a file without executing it
+    :reset        starts over with an empty vm
+    :stack        prints the stack and frames
+    :labels       prints the labels and their addresses
+    :undo         forgets the last line or block
+    :help         prints this
+    :quit         leaves the repl";
+
+// a line or block the repl accepted, kept so the session can be rebuilt without it.
+struct Entry {
+    source: String,
+    execute: bool,
+}
+
+// an interactive session: every line fed to it is assembled into a persistent vm,
+// and instructions are executed on the spot.
+pub struct Repl {
+    vm: UVM,
+    history: Vec<Entry>,
+    // lines of a label block that is still being entered.
+    block: Option<Vec<String>>,
+    finished: bool,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            vm: UVM::new(),
+            history: Vec::new(),
+            block: None,
+            finished: false,
+        }
+    }
+
+    pub fn vm(&self) -> &UVM {
+        &self.vm
+    }
+
+    // where the program's output goes, stdout by default.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.vm.set_output(output);
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.block.is_some() {
+            "... "
+        } else {
+            "uvm> "
+        }
+    }
+
+    // true once `:quit` was entered.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // handles one line of input, returning what to show for it.
+    pub fn feed(&mut self, line: &str) -> String {
+        let trimmed = line.trim();
+
+        if let Some(block) = &mut self.block {
+            if !trimmed.is_empty() {
+                block.push(line.to_string());
+                return String::new();
+            }
+            let source = self.block.take().unwrap().join("\n");
+            return match self.apply(Entry {
+                source,
+                execute: false,
+            }) {
+                Ok(()) => String::from("ok"),
+                Err(message) => message,
+            };
+        }
+
+        if let Some(command) = trimmed.strip_prefix(':') {
+            return self.command(command.trim());
+        }
+        if trimmed.is_empty() || trimmed.starts_with(';') {
+            return String::new();
+        }
+        if is_block_start(trimmed) {
+            self.block = Some(vec![line.to_string()]);
+            return String::new();
+        }
+
+        let execute = !trimmed.starts_with('.');
+        match self.apply(Entry {
+            source: line.to_string(),
+            execute,
+        }) {
+            Ok(()) if execute && self.vm.is_halted() => {
+                format!("halted with {}\n{:?}", self.vm.exit_code(), self.vm.stack())
+            }
+            Ok(()) if execute => format!("{:?}", self.vm.stack()),
+            Ok(()) => String::from("ok"),
+            Err(message) => message,
+        }
+    }
+
+    fn command(&mut self, command: &str) -> String {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+
+        match name {
+            "load" if !argument.is_empty() => {
+                let source = match read_to_string(argument) {
+                    Ok(source) => source,
+                    Err(err) => return format!("ERROR: {}: {}", argument, err),
+                };
+                let before = self.vm.program().len();
+                match self.apply(Entry {
+                    source,
+                    execute: false,
+                }) {
+                    Ok(()) => format!("loaded {} instructions", self.vm.program().len() - before),
+                    Err(message) => message,
+                }
+            }
+            "reset" => {
+                self.history.clear();
+                self.rebuild();
+                String::from("ok")
+            }
+            "stack" => {
+                let mut output = format!("stack: {:?}", self.vm.stack());
+                if !self.vm.frames().is_empty() {
+                    output.push('\n');
+                    output.push_str(&self.vm.format_frames());
+                }
+                output
+            }
+            "labels" => {
+                let labels: Vec<String> = self
+                    .vm
+                    .symbols()
+                    .labels_by_address()
+                    .iter()
+                    .map(|label| {
+                        let kind = match label.kind {
+                            SymbolKind::DataLabel => "data",
+                            _ => "code",
+                        };
+                        format!("{} {} {}", label.name, kind, label.address().unwrap())
+                    })
+                    .collect();
+                labels.join("\n")
+            }
+            "undo" => {
+                if self.history.pop().is_none() {
+                    return String::from("nothing to undo");
+                }
+                self.rebuild();
+                format!("{:?}", self.vm.stack())
+            }
+            "help" => String::from(HELP),
+            "quit" | "q" => {
+                self.finished = true;
+                String::new()
+            }
+            _ => String::from("unknown command, see :help"),
+        }
+    }
+
+    // assembles an entry onto the end of the program and executes it when asked to. when
+    // that fails the session is rebuilt as it was before the entry.
+    fn apply(&mut self, entry: Entry) -> Result<(), String> {
+        match self.run_entry(&entry) {
+            Ok(()) => {
+                self.history.push(entry);
+                Ok(())
+            }
+            Err(message) => {
+                self.rebuild();
+                Err(message)
+            }
+        }
+    }
+
+    fn run_entry(&mut self, entry: &Entry) -> Result<(), String> {
+        let start = self.vm.program().len();
+        if let Some(errors) = self.vm.append_program(&entry.source) {
+            let errors: Vec<String> = errors
+                .iter()
+                .map(|err| format!("LexingError: {}", err))
+                .collect();
+            return Err(errors.join("\n"));
+        }
+        if !entry.execute {
+            return Ok(());
+        }
+
+        // the new instructions are the last ones, so they are done once execution
+        // runs off the end of the program.
+        let end = self.vm.program().len();
+        self.vm.set_instruction_pointer(start);
+        for _ in 0..STEP_LIMIT {
+            if self.vm.instruction_pointer() == end || self.vm.is_halted() {
+                return Ok(());
+            }
+            if let Some(context) = self.vm.step() {
+                return Err(format!("Trap: {}", context));
+            }
+        }
+        Err(format!("stopped after {} instructions", STEP_LIMIT))
+    }
+
+    // replays the history into a fresh vm, without repeating its output.
+    fn rebuild(&mut self) {
+        let output = self.vm.set_output(Box::new(sink()));
+        self.vm = UVM::new();
+        self.vm.set_output(Box::new(sink()));
+
+        // every entry worked when it was entered, and replaying it does the same.
+        let history = std::mem::take(&mut self.history);
+        for entry in &history {
+            self.run_entry(entry).ok();
+        }
+        self.history = history;
+        self.vm.set_output(output);
+    }
+}
+
+fn is_block_start(line: &str) -> bool {
+    // a comment may follow the label.
+    let code = match line.split_once(';') {
+        Some((code, _)) => code.trim(),
+        None => line,
+    };
+    code.ends_with(':') || code == ".data"
+}
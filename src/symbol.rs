@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::lexer::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    CodeLabel,
+    DataLabel,
+    Constant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    // local labels (`@name`), only visible by their short name inside their scope.
+    Local,
+    Global,
+    // marked with `.export`, for hosts and tools that look symbols up by name.
+    Exported,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // the instruction index of a code label, the memory address of a data label,
+    // or the value of a constant.
+    pub value: Value,
+    pub line: usize,
+    pub visibility: Visibility,
+}
+
+impl Symbol {
+    pub fn new(
+        name: String,
+        kind: SymbolKind,
+        value: Value,
+        line: usize,
+        visibility: Visibility,
+    ) -> Self {
+        Self {
+            name,
+            kind,
+            value,
+            line,
+            visibility,
+        }
+    }
+
+    pub fn address(&self) -> Option<usize> {
+        match (self.kind, self.value) {
+            (SymbolKind::CodeLabel | SymbolKind::DataLabel, Value::Integer(address)) => {
+                Some(address as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self {
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.symbols.contains_key(name)
+    }
+
+    // returns false (and keeps the existing symbol) if the name is already taken.
+    pub fn insert(&mut self, symbol: Symbol) -> bool {
+        if self.symbols.contains_key(&symbol.name) {
+            return false;
+        }
+        self.symbols.insert(symbol.name.to_string(), symbol);
+        true
+    }
+
+    // returns false if there is no such symbol.
+    pub fn export(&mut self, name: &str) -> bool {
+        match self.symbols.get_mut(name) {
+            Some(symbol) => {
+                symbol.visibility = Visibility::Exported;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.values()
+    }
+
+    // code labels ordered by instruction index, followed by data labels ordered by memory address.
+    // labels sharing an address are ordered by name, so the order is stable.
+    pub fn labels_by_address(&self) -> Vec<&Symbol> {
+        let mut labels: Vec<&Symbol> = self
+            .symbols
+            .values()
+            .filter(|symbol| symbol.address().is_some())
+            .collect();
+        labels.sort_by(|a, b| {
+            let a_key = (a.kind == SymbolKind::DataLabel, a.address(), &a.name);
+            let b_key = (b.kind == SymbolKind::DataLabel, b.address(), &b.name);
+            a_key.cmp(&b_key)
+        });
+        labels
+    }
+
+    // the closest code label at or before an instruction index, and how far past it the index is.
+    pub fn code_location(&self, address: usize) -> Option<(String, usize)> {
+        self.symbols
+            .values()
+            .filter(|symbol| symbol.kind == SymbolKind::CodeLabel)
+            .filter_map(|symbol| Some((symbol, symbol.address()?)))
+            .filter(|(_, label_address)| *label_address <= address)
+            // the nearest label wins; at the same address global names win over local ones.
+            .min_by_key(|(symbol, label_address)| {
+                (
+                    address - label_address,
+                    symbol.visibility == Visibility::Local,
+                    &symbol.name,
+                )
+            })
+            .map(|(symbol, label_address)| (symbol.name.to_string(), address - label_address))
+    }
+}
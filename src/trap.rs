@@ -1,8 +1,135 @@
-#[derive(Debug)]
+use std::fmt;
+
+use crate::{global::Float, instruction::Instruction, limits::Limit};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Trap {
     StackUnderflow,
     DivisionByZero,
     InvalidInstructionPointer,
+    InvalidMemoryAddress,
     IllegalOperation,
     IllegalOperand,
+    NativeError(String),
+    CallStackUnderflow,
+    UncaughtThrow(Float),
+    InvalidFrame,
+    StackOverflow,
+    LimitExceeded(Limit),
+    IntegerOverflow,
+}
+
+impl Trap {
+    // the number a `.trap` handler finds on top of the stack.
+    pub fn code(&self) -> u8 {
+        match self {
+            Trap::StackUnderflow => 1,
+            Trap::DivisionByZero => 2,
+            Trap::InvalidInstructionPointer => 3,
+            Trap::InvalidMemoryAddress => 4,
+            Trap::IllegalOperation => 5,
+            Trap::IllegalOperand => 6,
+            Trap::NativeError(_) => 7,
+            Trap::CallStackUnderflow => 8,
+            Trap::UncaughtThrow(_) => 9,
+            Trap::InvalidFrame => 10,
+            Trap::StackOverflow => 11,
+            Trap::LimitExceeded(_) => 12,
+            Trap::IntegerOverflow => 13,
+        }
+    }
+
+    // the code of the trap with this name, as written in `.trap <name> <handler>`.
+    // running into a resource limit can not be handled, so those traps have no name here.
+    pub fn code_from_name(name: &str) -> Option<u8> {
+        match name {
+            "StackUnderflow" => Some(1),
+            "DivisionByZero" => Some(2),
+            "InvalidInstructionPointer" => Some(3),
+            "InvalidMemoryAddress" => Some(4),
+            "IllegalOperation" => Some(5),
+            "IllegalOperand" => Some(6),
+            "NativeError" => Some(7),
+            "CallStackUnderflow" => Some(8),
+            "UncaughtThrow" => Some(9),
+            "InvalidFrame" => Some(10),
+            "IntegerOverflow" => Some(13),
+            _ => None,
+        }
+    }
+
+    pub fn name_from_code(code: u8) -> Option<&'static str> {
+        match code {
+            1 => Some("StackUnderflow"),
+            2 => Some("DivisionByZero"),
+            3 => Some("InvalidInstructionPointer"),
+            4 => Some("InvalidMemoryAddress"),
+            5 => Some("IllegalOperation"),
+            6 => Some("IllegalOperand"),
+            7 => Some("NativeError"),
+            8 => Some("CallStackUnderflow"),
+            9 => Some("UncaughtThrow"),
+            10 => Some("InvalidFrame"),
+            13 => Some("IntegerOverflow"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::NativeError(message) => write!(f, "NativeError: {}", message),
+            Trap::UncaughtThrow(value) => write!(f, "UncaughtThrow: {}", value),
+            Trap::LimitExceeded(limit) => write!(f, "LimitExceeded: {}", limit),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// a trap no handler was installed for, with where it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrapContext {
+    pub trap: Trap,
+    pub instruction_pointer: usize,
+    pub instruction: Option<Instruction>,
+    // the closest code label at or before the instruction, and the distance from it.
+    pub location: Option<(String, usize)>,
+}
+
+impl TrapContext {
+    pub fn position(&self) -> TrapPosition<'_> {
+        TrapPosition {
+            instruction_pointer: self.instruction_pointer,
+            instruction: self.instruction.as_ref(),
+            location: self.location.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for TrapContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.trap, self.position())
+    }
+}
+
+// where a trap happened, as written after its name: ` at 3 (div) in main+2`.
+pub struct TrapPosition<'a> {
+    pub instruction_pointer: usize,
+    pub instruction: Option<&'a Instruction>,
+    pub location: Option<&'a (String, usize)>,
+}
+
+impl fmt::Display for TrapPosition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, " at {}", self.instruction_pointer)?;
+        if let Some(instruction) = self.instruction {
+            write!(f, " ({})", instruction)?;
+        }
+        match self.location {
+            Some((label, 0)) => write!(f, " in {}", label),
+            Some((label, offset)) => write!(f, " in {}+{}", label, offset),
+            None => Ok(()),
+        }
+    }
 }
@@ -0,0 +1,121 @@
+use uvm::{
+    core::{RunStatus, UVM},
+    error::BytecodeError,
+};
+
+const SOURCE: &str = "
+.trap DivisionByZero on_division_by_zero
+.export main
+.data
+.greeting:
+.string \"hi\"
+.text
+.main:
+push greeting
+push 0.5
+push nan
+push -0.0
+push 1e300
+call twice
+hlt
+.twice:
+@double:
+dup 0
+add
+ret
+.on_division_by_zero:
+hlt
+";
+
+fn assemble(source: &str) -> UVM {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+    vm
+}
+
+fn same_floats(a: &[f64], b: &[f64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.to_bits() == b.to_bits())
+}
+
+#[test]
+fn bytecode_round_trips() {
+    let vm = assemble(SOURCE);
+    let bytes = vm.to_bytecode();
+
+    let mut loaded = UVM::new();
+    assert_eq!(loaded.load_bytecode(&bytes), None);
+    assert_eq!(loaded.memory(), vm.memory());
+    assert_eq!(loaded.program().len(), vm.program().len());
+    assert_eq!(loaded.symbols().len(), vm.symbols().len());
+    assert_eq!(
+        loaded.symbols().get("twice@double").unwrap().address(),
+        Some(7)
+    );
+    assert_eq!(loaded.to_bytecode(), bytes);
+
+    assert_eq!(loaded.execute(), Ok(RunStatus::Halted(0)));
+    let mut original = assemble(SOURCE);
+    assert_eq!(original.execute(), Ok(RunStatus::Halted(0)));
+    assert!(same_floats(loaded.stack(), original.stack()));
+}
+
+#[test]
+fn broken_bytecode_is_rejected() {
+    let bytes = assemble(SOURCE).to_bytecode();
+    let mut vm = UVM::new();
+
+    assert_eq!(
+        vm.load_bytecode(b"push 1"),
+        Some(BytecodeError::NotBytecode)
+    );
+    assert_eq!(
+        vm.load_bytecode(&bytes[..bytes.len() - 1]),
+        Some(BytecodeError::UnexpectedEnd)
+    );
+
+    let mut version = bytes.clone();
+    version[4] = 99;
+    assert_eq!(
+        vm.load_bytecode(&version),
+        Some(BytecodeError::UnsupportedVersion(99))
+    );
+
+    // the first instruction's opcode follows the header and the instruction count.
+    let mut opcode = bytes.clone();
+    opcode[9] = 0x7f;
+    assert_eq!(
+        vm.load_bytecode(&opcode),
+        Some(BytecodeError::IllegalOpcode(0x7f))
+    );
+
+    let mut trailing = bytes;
+    trailing.push(0);
+    assert_eq!(
+        vm.load_bytecode(&trailing),
+        Some(BytecodeError::TrailingBytes)
+    );
+}
+
+#[test]
+fn disassembly_assembles_back_into_the_same_program() {
+    let vm = assemble(SOURCE);
+    let text = vm.disassemble();
+    assert!(text.contains(".trap DivisionByZero on_division_by_zero\n"));
+    assert!(text.contains("call twice\n"));
+    assert!(text.contains("push NaN\n"));
+    assert!(text.contains("push -0.0\n"));
+
+    let reassembled = assemble(&text);
+    assert_eq!(reassembled.memory(), vm.memory());
+    assert_eq!(reassembled.program().len(), vm.program().len());
+    for (a, b) in reassembled.program().iter().zip(vm.program()) {
+        assert_eq!(a.instruction_type, b.instruction_type);
+        assert_eq!(a.operand.map(f64::to_bits), b.operand.map(f64::to_bits));
+    }
+    // local labels can only be written back as comments.
+    let without_comments: Vec<&str> = text.lines().filter(|line| !line.starts_with(';')).collect();
+    assert_eq!(
+        reassembled.disassemble(),
+        without_comments.join("\n") + "\n"
+    );
+}
@@ -0,0 +1,87 @@
+// exercises the c api through the shared library. prints what went wrong and exits with 1
+// on the first failed check.
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "uvm.h"
+
+#define CHECK(condition)                                                    \
+    do {                                                                    \
+        if (!(condition)) {                                                 \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #condition);                                            \
+            exit(1);                                                        \
+        }                                                                   \
+    } while (0)
+
+struct buffer {
+    char text[256];
+    size_t length;
+};
+
+static void collect(void *user_data, const uint8_t *bytes, size_t length) {
+    struct buffer *buffer = user_data;
+    CHECK(buffer->length + length < sizeof(buffer->text));
+    memcpy(buffer->text + buffer->length, bytes, length);
+    buffer->length += length;
+    buffer->text[buffer->length] = '\0';
+}
+
+static int load(UvmVm *vm, const char *source) {
+    return uvm_load(vm, (const uint8_t *)source, strlen(source));
+}
+
+static void runs_a_program(void) {
+    UvmVm *vm = uvm_new();
+    struct buffer output = {.length = 0};
+    uvm_set_output(vm, collect, &output);
+
+    CHECK(load(vm, "push 6\npush 7\nmul\nout\npush 1\nhlt 3") == UVM_OK);
+    CHECK(uvm_last_error(vm) == NULL);
+    CHECK(uvm_run(vm, 0) == UVM_HALTED);
+    CHECK(uvm_exit_code(vm) == 3);
+    CHECK(strcmp(output.text, "42\n") == 0);
+
+    double stack[4];
+    CHECK(uvm_stack(vm, NULL, 0) == 2);
+    CHECK(uvm_stack(vm, stack, 4) == 2);
+    CHECK(stack[0] == 42.0 && stack[1] == 1.0);
+    uvm_free(vm);
+}
+
+static void steps_and_runs_with_a_budget(void) {
+    UvmVm *vm = uvm_new();
+    CHECK(load(vm, "push 1\npush 2\nadd\nhlt") == UVM_OK);
+    CHECK(uvm_step(vm) == UVM_PAUSED);
+    CHECK(uvm_run(vm, 1) == UVM_PAUSED);
+
+    double top;
+    CHECK(uvm_stack(vm, &top, 1) == 2);
+    CHECK(top == 1.0);
+    CHECK(uvm_step(vm) == UVM_PAUSED);
+    CHECK(uvm_step(vm) == UVM_HALTED);
+    CHECK(uvm_step(vm) == UVM_HALTED);
+    uvm_free(vm);
+}
+
+static void reports_errors(void) {
+    UvmVm *vm = uvm_new();
+    CHECK(load(vm, "push 1\nbogus") == UVM_ERROR);
+    CHECK(strcmp(uvm_last_error(vm), "LexingError: line 2: IllegalOperation") == 0);
+
+    CHECK(load(vm, "push 1\npush 0\ndiv") == UVM_OK);
+    CHECK(uvm_last_error(vm) == NULL);
+    CHECK(uvm_run(vm, 0) == UVM_TRAPPED);
+    CHECK(strcmp(uvm_last_error(vm), "Trap: DivisionByZero at 2 (div)") == 0);
+    uvm_free(vm);
+    uvm_free(NULL);
+}
+
+int main(void) {
+    runs_a_program();
+    steps_and_runs_with_a_budget();
+    reports_errors();
+    printf("ok\n");
+    return 0;
+}
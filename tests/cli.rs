@@ -0,0 +1,158 @@
+use std::{
+    env::temp_dir,
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+fn write_program(name: &str, source: &str) -> PathBuf {
+    let path = temp_dir().join(format!("uvm-cli-{}-{}.uasm", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn uvm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_uvm"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn run_prints_program_output() {
+    let path = write_program("run", "push 6\npush 7\nmul\nout\nhlt");
+    let output = uvm(&["run", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "42\n");
+}
+
+#[test]
+fn run_exits_with_the_halt_code() {
+    let path = write_program("halt", "push 1\nout\nhlt 3");
+    let output = uvm(&["run", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "1\n");
+}
+
+#[test]
+fn failures_have_distinct_exit_codes() {
+    let bad = write_program("bad", "bogus");
+    let output = uvm(&["run", bad.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr(&output).contains("LexingError: line 1: IllegalOperation"));
+
+    let trap = write_program("trap", "push 1\npush 0\ndiv");
+    let output = uvm(&["run", trap.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).contains("Trap: DivisionByZero at 2 (div)"));
+
+    assert_eq!(uvm(&["run", "/nonexistent.uasm"]).status.code(), Some(66));
+    assert_eq!(uvm(&["frobnicate"]).status.code(), Some(64));
+    assert_eq!(uvm(&["emulate", "x.uasm"]).status.code(), Some(64));
+}
+
+#[test]
+fn check_only_assembles() {
+    let path = write_program("check", ".unused:\npush 1\nout\nhlt");
+    let output = uvm(&["check", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("UnusedLabel `unused`"));
+
+    let output = uvm(&["check", "--quiet", path.to_str().unwrap()]);
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn emulate_stops_after_the_limit() {
+    let path = write_program("emulate", ".loop:\npush 1\nout\npop\njmp loop");
+    let output = uvm(&["emulate", "--limit", "6", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1\n1\n");
+}
+
+#[test]
+fn stack_limit_is_enforced() {
+    let path = write_program("stack", ".loop:\npush 1\njmp loop");
+    let output = uvm(&["run", "--stack-limit", "10", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).contains("StackOverflow"));
+}
+
+#[test]
+fn assembled_bytecode_runs_and_disassembles() {
+    let path = write_program(
+        "asm",
+        ".main:\npush 2\ncall square\nout\nhlt\n.square:\ndup 0\nmul\nret",
+    );
+    let bytecode = path.with_extension("uvmb");
+    let output = uvm(&["asm", "-q", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = uvm(&["run", bytecode.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "4\n");
+
+    let output = uvm(&["disasm", bytecode.to_str().unwrap()]);
+    assert_eq!(
+        stdout(&output),
+        ".main:\npush 2\ncall square\nout\nhlt\n.square:\ndup 0\nmul\nret\n"
+    );
+}
+
+#[test]
+fn trace_shows_each_instruction() {
+    let path = write_program("trace", "push 1\npush 2\nadd\nhlt");
+    let output = uvm(&["trace", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stderr(&output).contains("    2  add                  [3.0]\n"));
+}
+
+#[test]
+fn numeric_files_and_integer_arithmetic() {
+    let path = temp_dir().join(format!("uvm-cli-{}-numeric.uvmn", std::process::id()));
+    fs::write(&path, "0 7\n0 2\n22\n25\n29\n").unwrap();
+
+    let output = uvm(&["run", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3.5\n");
+
+    let output = uvm(&["run", "--integer", path.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "3\n");
+
+    let output = uvm(&["disasm", path.to_str().unwrap()]);
+    assert!(stdout(&output).contains("push 7\npush 2\ndiv\nout\nhlt\n"));
+}
+
+#[test]
+fn the_jit_runs_programs_the_same() {
+    let path = write_program("jit", "push 6\npush 7\nmul\nout\npush 0\ndiv\nhlt");
+    let interpreted = uvm(&["run", path.to_str().unwrap()]);
+    let compiled = uvm(&["run", "--jit", path.to_str().unwrap()]);
+    assert_eq!(compiled.status.code(), Some(70));
+    assert_eq!(compiled.stdout, interpreted.stdout);
+    assert_eq!(stdout(&compiled), "42\n");
+    assert_eq!(stderr(&compiled), "Trap: DivisionByZero at 5 (div)\n");
+}
+
+#[test]
+fn registers_run_programs_the_same() {
+    let path = write_program("registers", "push 6\npush 7\nmul\nout\npush 0\ndiv\nhlt");
+    let translated = uvm(&["run", "--registers", path.to_str().unwrap()]);
+    assert_eq!(translated.status.code(), Some(70));
+    assert_eq!(stdout(&translated), "42\n");
+    assert_eq!(stderr(&translated), "Trap: DivisionByZero at 5 (div)\n");
+    assert_eq!(
+        uvm(&["run", "--registers", "--jit", path.to_str().unwrap()])
+            .status
+            .code(),
+        Some(64)
+    );
+}
@@ -0,0 +1,308 @@
+use std::{
+    env::{self, temp_dir},
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use uvm::{
+    bytecode::Image,
+    compile::to_c,
+    core::UVM,
+    error::CompileError,
+    instruction::{Instruction, InstructionType},
+    numeric::Numeric,
+};
+
+fn uvm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_uvm"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn temporary(name: &str) -> PathBuf {
+    temp_dir().join(format!("uvm-compile-{}-{}", std::process::id(), name))
+}
+
+// compiles the program with `uvm compile` and a c compiler, runs it and checks that it printed
+// and exited exactly like `uvm run`.
+fn assert_compiles_like_run(path: &Path) {
+    let path = path.to_str().unwrap();
+    let expected = uvm(&["run", "--quiet", path]);
+
+    let name = Path::new(path).file_stem().unwrap().to_str().unwrap();
+    let c = temporary(&format!("{}.c", name));
+    let executable = temporary(name);
+    let compiled = uvm(&["compile", "--to", "c", path, "-o", c.to_str().unwrap()]);
+    assert!(
+        compiled.status.success(),
+        "{}: {}",
+        path,
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+
+    let built = Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-O1"])
+        .arg(&c)
+        .arg("-o")
+        .arg(&executable)
+        .arg("-lm")
+        .output()
+        .expect("a c compiler is needed to run this test");
+    assert!(
+        built.status.success(),
+        "{}: {}",
+        path,
+        String::from_utf8_lossy(&built.stderr)
+    );
+
+    let actual = Command::new(&executable).output().unwrap();
+    fs::remove_file(&c).ok();
+    fs::remove_file(&executable).ok();
+    // compared as bytes, since invalid utf-8 is part of what is checked.
+    assert!(
+        actual.stdout == expected.stdout,
+        "{}: stdout differs\n{:?}\n{:?}",
+        path,
+        String::from_utf8_lossy(&actual.stdout),
+        String::from_utf8_lossy(&expected.stdout)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&actual.stderr),
+        String::from_utf8_lossy(&expected.stderr),
+        "{}",
+        path
+    );
+    assert_eq!(actual.status.code(), expected.status.code(), "{}", path);
+}
+
+fn write_program(name: &str, source: &str) -> PathBuf {
+    let path = temporary(&format!("{}.uasm", name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn golden_programs_compile() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        // programs that do not assemble have nothing to compile.
+        if uvm(&["check", path.to_str().unwrap()]).status.success() {
+            assert_compiles_like_run(&path);
+        }
+    }
+}
+
+#[test]
+fn numbers_are_formatted_like_the_interpreter() {
+    let mut values = vec![
+        0.,
+        -0.,
+        1.,
+        -1.5,
+        0.1,
+        1. / 3.,
+        100.,
+        1e15,
+        1e16,
+        1.5e16,
+        123456789012345680.,
+        1e-4,
+        1e-5,
+        1.5e-5,
+        f64::MIN_POSITIVE,
+        5e-324,
+        f64::MAX,
+        -f64::MAX,
+        1e300,
+        2f64.powi(53),
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+        -f64::NAN,
+        f64::from_bits(0x7ff8_0000_dead_beef),
+    ];
+    // and whatever xorshift comes up with, over the whole range of exponents.
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for _ in 0..300 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        values.push(f64::from_bits(state));
+    }
+
+    let mut program = Vec::new();
+    for value in &values {
+        program.push(Instruction::new(InstructionType::Push, Some(*value)));
+        program.push(Instruction::new(InstructionType::Output, None));
+        program.push(Instruction::new(InstructionType::Outputf, None));
+    }
+    program.push(Instruction::new(InstructionType::Dump, None));
+    program.push(Instruction::new(InstructionType::Halt, None));
+    let image = Image {
+        program,
+        ..Image::default()
+    };
+
+    let path = temporary("numbers.uvmb");
+    fs::write(&path, image.encode()).unwrap();
+    assert_compiles_like_run(&path);
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn control_flow_and_traps_compile() {
+    let programs = [
+        (
+            "functions",
+            "
+            .trap DivisionByZero recover
+                push 10
+                call half
+                out
+                push 3
+                call half
+                dmp
+                push 0
+                div
+                hlt 1
+            .recover:
+                dmp
+                pop
+                pop
+                push 0
+                try caught
+                call deep
+                endtry
+                hlt 3
+            .caught:
+                out
+                ret
+            .deep:
+                enter 2
+                push 42
+                lstore 1
+                dmp
+                lload 1
+                throw
+            .half:
+                enter 1
+                aload 0
+                push 2
+                div
+                lstore 0
+                lload 0
+                leave
+                swp 1
+                pop
+                ret
+            ",
+        ),
+        (
+            "strings",
+            "
+            .data
+            .plain:
+            .string \"héllo \\n\"
+            .broken:
+            .bytes 104, 255, 105, 0xe2, 0x82, 33, 0xf0, 0x9f, 0x98, 0x80, 0xed, 0xa0, 0x80, 10, 0
+            .text
+                push plain
+                outs
+                push broken
+                outs
+                push 0x1F600
+                outc
+                push 0xD800
+                outc
+            ",
+        ),
+        (
+            "comparisons",
+            "
+                push 3
+                push 1
+            .loop:
+                pop
+                push 1
+                sub
+                dup 0
+                out
+                push 0
+                geql
+                jmpif loop
+                push 0.5
+                push 0.5
+                geql
+                out
+                push 0.5
+                eql
+                not
+                out
+                hlt 7
+            ",
+        ),
+        ("end", "push 1\n.main:\npush 2\nadd\nout"),
+        ("uncaught", ".main:\npush 1\npush 2.5\nthrow"),
+        (
+            "handlers",
+            "
+            .trap UncaughtThrow thrown
+            .trap StackUnderflow underflow
+                push 5
+                throw
+            .thrown:
+                dmp
+                pop
+                pop
+                pop
+                pop
+            .underflow:
+                dmp
+                hlt 4
+            ",
+        ),
+        ("return", "push 1\nret"),
+        ("leave", "enter 1\nleave\nleave"),
+        ("frames", "push 7\npop\nlload 0\n"),
+    ];
+    for (name, source) in programs {
+        assert_compiles_like_run(&write_program(name, source));
+    }
+}
+
+#[test]
+fn only_plain_programs_compile() {
+    let mut vm = UVM::new();
+    vm.register_native("answer", 0, 1, |_| Ok(vec![42.]));
+    assert!(vm.load_program("push 1\nnative answer\nout").is_none());
+    assert_eq!(to_c(&vm), Err(CompileError::NativeCall(1)));
+
+    let mut vm = UVM::new();
+    vm.set_numeric(Numeric::Integer);
+    assert!(vm.load_program("push 1\nout").is_none());
+    assert_eq!(to_c(&vm), Err(CompileError::IntegerArithmetic));
+
+    let path = write_program("limit", "push 1\nout");
+    let output = uvm(&[
+        "compile",
+        "--to",
+        "c",
+        "--stack-limit",
+        "4",
+        path.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "CompileError: Limits\n"
+    );
+    assert_eq!(
+        uvm(&["compile", "--to", "wasm", path.to_str().unwrap()])
+            .status
+            .code(),
+        Some(64)
+    );
+}
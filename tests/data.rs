@@ -0,0 +1,157 @@
+mod common;
+
+use uvm::{
+    core::{RunStatus, UVM},
+    error::LexingError,
+    trap::Trap,
+};
+
+use common::SharedBuffer;
+
+const DATA: &str = "
+.data
+.greeting:
+.string \"hi\"
+.numbers:
+.bytes 1, 2, 0xff
+.values:
+.floats 1.5, 2
+.text
+push values
+outs
+hlt
+";
+
+fn first_error(source: &str) -> LexingError {
+    let mut vm = UVM::new();
+    vm.load_program(source)
+        .expect("the program should not assemble")
+        .remove(0)
+        .kind
+}
+
+// runs `source` and returns what it printed and how it stopped.
+fn run(source: &str) -> (String, Result<RunStatus, Trap>) {
+    let mut vm = UVM::new();
+    let output = SharedBuffer::default();
+    vm.set_output(Box::new(output.clone()));
+    assert!(vm.load_program(source).is_none());
+    let result = vm.execute().map_err(|context| context.trap);
+    (output.text(), result)
+}
+
+#[test]
+fn data_is_laid_out_in_the_order_it_is_defined() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(DATA).is_none());
+
+    let mut expected = b"hi\0".to_vec();
+    expected.extend_from_slice(&[1, 2, 0xff]);
+    expected.extend_from_slice(&1.5f64.to_le_bytes());
+    expected.extend_from_slice(&2f64.to_le_bytes());
+    assert_eq!(vm.memory(), expected.as_slice());
+}
+
+#[test]
+fn data_labels_are_memory_addresses() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(DATA).is_none());
+
+    let address = |name: &str| vm.symbols().get(name).unwrap().address();
+    assert_eq!(address("greeting"), Some(0));
+    assert_eq!(address("numbers"), Some(3));
+    assert_eq!(address("values"), Some(6));
+    assert_eq!(vm.program()[0].operand, Some(6.));
+}
+
+#[test]
+fn outs_prints_up_to_the_nul_and_outc_one_character() {
+    let source = "
+.data
+.first:
+.string \"one\"
+.second:
+.bytes 't', 'w', 'o', 0
+.text
+push first
+outs
+push second
+outs
+push 0x1F600
+outc
+hlt
+";
+    let (output, result) = run(source);
+    assert_eq!(output, "onetwo\u{1F600}");
+    assert_eq!(result, Ok(RunStatus::Halted(0)));
+}
+
+#[test]
+fn outs_needs_a_nul_terminated_string_in_memory() {
+    // two bytes without a nul after them.
+    let data = ".data\n.bytes 'a', 'b'\n.text\n";
+    for address in ["0", "1", "2", "-1", "0.5"] {
+        let (output, result) = run(&format!("{}push {}\nouts\nhlt", data, address));
+        assert_eq!(output, "");
+        assert_eq!(result, Err(Trap::InvalidMemoryAddress), "{}", address);
+    }
+
+    // bytes before a string run on into its nul.
+    let data = ".data\n.bytes 'a', 'b'\n.string \"c\"\n.text\n";
+    let (output, result) = run(&format!("{}push 0\nouts\nhlt", data));
+    assert_eq!(output, "abc");
+    assert_eq!(result, Ok(RunStatus::Halted(0)));
+}
+
+#[test]
+fn outc_needs_a_code_point() {
+    for code in ["-1", "1.5", "0xD800", "0x110000", "1e20"] {
+        let (output, result) = run(&format!("push {}\noutc\nhlt", code));
+        assert_eq!(output, "");
+        assert_eq!(result, Err(Trap::IllegalOperand), "{}", code);
+    }
+}
+
+#[test]
+fn data_and_code_stay_in_their_sections() {
+    assert!(matches!(
+        first_error(".string \"x\""),
+        LexingError::IllegalSection
+    ));
+    assert!(matches!(
+        first_error(".bytes 1"),
+        LexingError::IllegalSection
+    ));
+    assert!(matches!(
+        first_error(".floats 1"),
+        LexingError::IllegalSection
+    ));
+    assert!(matches!(
+        first_error(".data\npush 1"),
+        LexingError::IllegalSection
+    ));
+}
+
+#[test]
+fn data_values_must_fit_their_directive() {
+    assert!(matches!(
+        first_error(".data\n.bytes 256"),
+        LexingError::ExpressionOverflow
+    ));
+    assert!(matches!(
+        first_error(".data\n.bytes -1"),
+        LexingError::ExpressionOverflow
+    ));
+    assert!(matches!(
+        first_error(".data\n.bytes 1.5"),
+        LexingError::NonIntegerOperand
+    ));
+    assert!(matches!(
+        first_error(".data\n.string 5"),
+        LexingError::IllegalOperand
+    ));
+    assert!(matches!(
+        first_error(".data\n.bytes"),
+        LexingError::InvalidExpression
+    ));
+}
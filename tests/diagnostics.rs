@@ -0,0 +1,69 @@
+use uvm::{
+    core::UVM,
+    error::{LexingError, LexingWarning},
+};
+
+#[test]
+fn every_bad_line_is_reported() {
+    let mut vm = UVM::new();
+    let errors = vm
+        .load_program("push 1\npush FOO\nbogus\npush 2 +\nhlt")
+        .unwrap();
+
+    let lines: Vec<usize> = errors.iter().map(|err| err.line).collect();
+    assert_eq!(lines, vec![2, 3, 4]);
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedSymbol(name) if name == "FOO"));
+    assert!(matches!(errors[1].kind, LexingError::IllegalOperation));
+    assert!(matches!(errors[2].kind, LexingError::InvalidExpression));
+}
+
+#[test]
+fn error_count_is_capped() {
+    let mut vm = UVM::new();
+    vm.set_max_errors(2);
+    let errors = vm.load_program("bogus\nbogus\nbogus\nbogus").unwrap();
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn unused_labels_are_warned_about() {
+    let mut vm = UVM::new();
+    assert!(vm
+        .load_program(".start:\n.loop:\npush 1\njmp loop")
+        .is_none());
+
+    let warnings: Vec<String> = vm.warnings().iter().map(|w| w.to_string()).collect();
+    assert_eq!(warnings, vec!["line 1: UnusedLabel `start`"]);
+}
+
+#[test]
+fn code_after_hlt_or_jmp_is_unreachable_until_a_label() {
+    let mut vm = UVM::new();
+    assert!(vm
+        .load_program(".top:\njmp top\npush 1\npop\n.next:\npush 2\nhlt\nout")
+        .is_none());
+
+    let unreachable: Vec<usize> = vm
+        .warnings()
+        .iter()
+        .filter(|warning| matches!(warning.kind, LexingWarning::UnreachableCode))
+        .map(|warning| warning.line)
+        .collect();
+    assert_eq!(unreachable, vec![3, 8]);
+}
+
+#[test]
+fn deeply_nested_expressions_are_rejected() {
+    let mut vm = UVM::new();
+    let nested = format!("push {}1{}", "(".repeat(100), ")".repeat(100));
+    assert!(vm.load_program(&nested).is_none());
+
+    for source in [
+        format!("push {}1", "(".repeat(200_000)),
+        format!("push {}1", "-".repeat(200_000)),
+        format!(".data\n.bytes {}1", "~(".repeat(100_000)),
+    ] {
+        let errors = UVM::new().load_program(&source).unwrap();
+        assert!(matches!(errors[0].kind, LexingError::InvalidExpression));
+    }
+}
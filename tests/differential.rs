@@ -0,0 +1,213 @@
+mod common;
+mod reference;
+
+use std::collections::HashMap;
+
+use uvm::{
+    bytecode::Image,
+    core::{Engine, RunStatus, UVM},
+    instruction::{Instruction, InstructionType},
+    limits::VmLimits,
+};
+
+use common::SharedBuffer;
+
+const PROGRAMS: u64 = 3000;
+const BUDGET: u64 = 2000;
+const MEMORY: &[u8] = b"hello\0uvm\xff\0";
+
+// xorshift64*, so a failing seed can be replayed without any dependencies.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+}
+
+const VALUES: [f64; 14] = [
+    0., 1., 2., 3., 5., 10., -1., -2.5, 0.5, 1e10, 65., 90., 6., 1e300,
+];
+
+// programs whose operands pass load time validation, so every one of them runs.
+fn generate(random: &mut Random) -> Image {
+    let length = 1 + random.below(40);
+    let mut program = Vec::new();
+    for _ in 0..length {
+        // pushes are the most common, so the other instructions have something to work on.
+        let instruction_type = if random.below(2) == 0 {
+            InstructionType::Push
+        } else {
+            loop {
+                let instruction_type =
+                    InstructionType::from_opcode(random.below(30) as u8).unwrap();
+                if instruction_type != InstructionType::CallNative {
+                    break instruction_type;
+                }
+            }
+        };
+        let operand = match instruction_type {
+            InstructionType::Push => Some(random.pick(&VALUES)),
+            InstructionType::Duplicate => Some(random.below(4) as f64),
+            InstructionType::Swap => Some(1. + random.below(3) as f64),
+            InstructionType::Jump
+            | InstructionType::JumpIf
+            | InstructionType::Call
+            | InstructionType::Try => Some(random.below(length) as f64),
+            InstructionType::Enter
+            | InstructionType::LoadLocal
+            | InstructionType::StoreLocal
+            | InstructionType::LoadArgument => Some(random.below(4) as f64),
+            InstructionType::Halt if random.below(2) == 0 => {
+                Some(random.pick(&[0., 3., 255., 0.5]))
+            }
+            _ => None,
+        };
+        program.push(Instruction::new(instruction_type, operand));
+    }
+
+    let mut trap_handlers = Vec::new();
+    for _ in 0..random.below(4) {
+        let code = 1 + random.below(10) as u8;
+        if trap_handlers
+            .iter()
+            .all(|(handler_code, _)| *handler_code != code)
+        {
+            trap_handlers.push((code, random.below(length)));
+        }
+    }
+    trap_handlers.sort();
+
+    Image {
+        program,
+        memory: MEMORY.to_vec(),
+        trap_handlers,
+        ..Image::default()
+    }
+}
+
+fn run_vm(image: &Image, engine: Engine) -> reference::Outcome {
+    let mut vm = UVM::new();
+    vm.set_engine(engine);
+    assert_eq!(vm.load_bytecode(&image.encode()), None);
+    let output = SharedBuffer::default();
+    vm.set_output(Box::new(output.clone()));
+    vm.set_limits(VmLimits {
+        max_instructions: Some(BUDGET),
+        ..VmLimits::default()
+    });
+
+    let result = match vm.execute() {
+        Ok(RunStatus::Halted(code)) => Ok(code),
+        Ok(status) => panic!("unexpected {:?}", status),
+        Err(context) => Err((format!("{:?}", context.trap), context.instruction_pointer)),
+    };
+    let output = String::from_utf8(output.bytes()).unwrap();
+    reference::Outcome {
+        output,
+        stack: vm.stack().iter().map(|value| value.to_bits()).collect(),
+        result,
+    }
+}
+
+// runs every generated program on `engine` and on the interpreter, which the reference checks.
+fn compare_engines(engine: Engine, label: &str) {
+    for seed in 1..=PROGRAMS {
+        let mut random = Random(seed);
+        let image = generate(&mut random);
+        let expected = run_vm(&image, Engine::Interpreter);
+        let actual = run_vm(&image, engine);
+        if actual != expected {
+            let mut vm = UVM::new();
+            vm.load_bytecode(&image.encode());
+            panic!(
+                "seed {} differs\n{}\n{:<12} {:?}\ninterpreter: {:?}",
+                seed,
+                vm.disassemble(),
+                format!("{}:", label),
+                actual,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn random_programs_behave_like_the_reference() {
+    for seed in 1..=PROGRAMS {
+        let mut random = Random(seed);
+        let image = generate(&mut random);
+        let trap_handlers: HashMap<u8, usize> = image.trap_handlers.iter().copied().collect();
+        let expected = reference::run(&image.program, &image.memory, &trap_handlers, BUDGET);
+
+        let actual = run_vm(&image, Engine::Interpreter);
+        if actual != expected {
+            let mut vm = UVM::new();
+            vm.load_bytecode(&image.encode());
+            panic!(
+                "seed {} differs\n{}\nvm:        {:?}\nreference: {:?}",
+                seed,
+                vm.disassemble(),
+                actual,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn assembled_programs_behave_like_the_reference() {
+    let mut vm = UVM::new();
+    let source = "
+        .trap DivisionByZero recover
+        push 10
+        call half
+        out
+        push 0
+        div
+        hlt 1
+    .recover:
+        dmp
+        hlt 2
+    .half:
+        enter 1
+        aload 0
+        push 2
+        div
+        lstore 0
+        lload 0
+        leave
+        swp 1
+        pop
+        ret
+    ";
+    assert!(vm.load_program(source).is_none());
+    let image = Image::decode(&vm.to_bytecode()).unwrap();
+    let trap_handlers: HashMap<u8, usize> = image.trap_handlers.iter().copied().collect();
+
+    let expected = reference::run(&image.program, &image.memory, &trap_handlers, BUDGET);
+    assert_eq!(expected.result, Ok(2));
+    assert_eq!(run_vm(&image, Engine::Interpreter), expected);
+}
+
+#[test]
+#[cfg(feature = "jit")]
+fn random_programs_behave_the_same_under_the_jit() {
+    compare_engines(Engine::Jit, "jit");
+}
+
+#[test]
+fn random_programs_behave_the_same_with_registers() {
+    compare_engines(Engine::Register, "registers");
+}
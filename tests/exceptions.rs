@@ -0,0 +1,108 @@
+use uvm::{core::UVM, trap::Trap};
+
+fn run(source: &str) -> (UVM, Option<Trap>) {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+    let trap = vm.execute().err().map(|context| context.trap);
+    (vm, trap)
+}
+
+#[test]
+fn call_and_ret_return_to_the_caller() {
+    let source = "
+push 1
+call double
+hlt
+.double:
+push 2
+mul
+ret
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, None);
+    assert_eq!(vm.stack(), &[2.]);
+}
+
+#[test]
+fn ret_without_call_traps() {
+    let (_, trap) = run("ret");
+    assert_eq!(trap, Some(Trap::CallStackUnderflow));
+}
+
+#[test]
+fn throw_unwinds_the_data_stack_to_the_try() {
+    let source = "
+push 10
+try @caught
+push 20
+push 30
+push 99
+throw
+endtry
+hlt
+@caught:
+hlt
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, None);
+    assert_eq!(vm.stack(), &[10., 99.]);
+}
+
+#[test]
+fn throw_unwinds_nested_calls() {
+    let source = "
+try @caught
+call outer
+endtry
+hlt
+@caught:
+hlt
+.outer:
+push 1
+call inner
+ret
+.inner:
+push 2
+push 7
+throw
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, None);
+    assert_eq!(vm.stack(), &[7.]);
+}
+
+#[test]
+fn endtry_leaves_the_try_block() {
+    let source = "
+try @caught
+endtry
+push 5
+throw
+@caught:
+hlt
+";
+    let (_, trap) = run(source);
+    assert_eq!(trap, Some(Trap::UncaughtThrow(5.)));
+}
+
+#[test]
+fn try_blocks_end_when_their_call_returns() {
+    let source = "
+call leaky
+push 3
+throw
+.leaky:
+try @caught
+ret
+@caught:
+hlt
+";
+    let (_, trap) = run(source);
+    assert_eq!(trap, Some(Trap::UncaughtThrow(3.)));
+}
+
+#[test]
+fn endtry_without_try_traps() {
+    let (_, trap) = run("endtry");
+    assert_eq!(trap, Some(Trap::IllegalOperation));
+}
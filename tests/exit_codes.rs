@@ -0,0 +1,41 @@
+use uvm::{
+    core::{RunStatus, UVM},
+    error::LexingError,
+    trap::Trap,
+};
+
+fn execute(source: &str) -> (UVM, Result<RunStatus, Trap>) {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+    let result = vm.execute().map_err(|context| context.trap);
+    (vm, result)
+}
+
+#[test]
+fn hlt_exits_with_its_operand() {
+    let (vm, result) = execute(".const FAILED 3\npush 1\nhlt FAILED");
+    assert_eq!(result, Ok(RunStatus::Halted(3)));
+    assert_eq!(vm.exit_code(), 3);
+}
+
+#[test]
+fn plain_hlt_exits_with_zero() {
+    let (_, result) = execute("push 9\nhlt");
+    assert_eq!(result, Ok(RunStatus::Halted(0)));
+}
+
+#[test]
+fn exit_codes_must_fit_in_a_byte() {
+    for code in ["256", "-1", "1.5", "nan"] {
+        let (vm, result) = execute(&format!("hlt {}", code));
+        assert_eq!(result, Err(Trap::IllegalOperand));
+        assert!(!vm.is_halted());
+    }
+}
+
+#[test]
+fn hlt_takes_at_most_one_operand_expression() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program("hlt 1 2").unwrap();
+    assert!(matches!(errors[0].kind, LexingError::InvalidExpression));
+}
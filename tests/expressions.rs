@@ -0,0 +1,132 @@
+use uvm::{core::UVM, error::LexingError};
+
+// the operand `push <expression>` assembles to, or the first error.
+fn push(expression: &str) -> Result<f64, LexingError> {
+    let mut vm = UVM::new();
+    match vm.load_program(&format!("push {}\nhlt", expression)) {
+        Some(mut errors) => Err(errors.remove(0).kind),
+        None => Ok(vm.program()[0].operand.unwrap()),
+    }
+}
+
+#[test]
+fn arithmetic_follows_the_usual_precedence() {
+    assert_eq!(push("1 + 2 * 3").unwrap(), 7.);
+    assert_eq!(push("(1 + 2) * 3").unwrap(), 9.);
+    assert_eq!(push("10 - 4 - 3").unwrap(), 3.);
+    assert_eq!(push("2 * (3 + (4 - 1))").unwrap(), 12.);
+    assert_eq!(push("7 / 2").unwrap(), 3.);
+    assert_eq!(push("7 % 3").unwrap(), 1.);
+    assert_eq!(push("7.0 / 2").unwrap(), 3.5);
+    assert_eq!(push("-2 * 3").unwrap(), -6.);
+    assert_eq!(push("- -3 + +1").unwrap(), 4.);
+}
+
+#[test]
+fn bitwise_operators_bind_looser_than_arithmetic() {
+    assert_eq!(push("6 & 3").unwrap(), 2.);
+    assert_eq!(push("6 | 3").unwrap(), 7.);
+    assert_eq!(push("6 ^ 3").unwrap(), 5.);
+    assert_eq!(push("~0").unwrap(), -1.);
+    assert_eq!(push("1 << 4").unwrap(), 16.);
+    assert_eq!(push("256 >> 4").unwrap(), 16.);
+    assert_eq!(push("1 + 1 << 2").unwrap(), 8.);
+    assert_eq!(push("1 | 2 & 3").unwrap(), 3.);
+    assert_eq!(push("2 | 1 ^ 3").unwrap(), 2.);
+    assert_eq!(push("12 & 10 ^ 15").unwrap(), 7.);
+}
+
+#[test]
+fn constants_and_labels_can_be_used_in_expressions() {
+    let mut vm = UVM::new();
+    let source = "
+.const SIZE 4
+.const LAST SIZE - 1
+    push SIZE * 4 + 1
+    push LAST
+.loop:
+    pop
+    jmp loop + 2
+    hlt
+";
+    assert!(vm.load_program(source).is_none());
+    let operands: Vec<Option<f64>> = vm.program().iter().map(|i| i.operand).collect();
+    assert_eq!(operands, [Some(17.), Some(3.), None, Some(4.), None]);
+}
+
+#[test]
+fn overflow_is_an_error() {
+    assert!(matches!(
+        push("(1 << 62) * 4"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+    assert!(matches!(
+        push("1 << 63"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+    assert!(matches!(
+        push("1 << 64"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+    assert!(matches!(
+        push("1 << -1"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+    assert!(matches!(
+        push("1e308 * 10"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+    // integers have to stay exact once they are operands.
+    assert!(matches!(
+        push("(1 << 53) + 1"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    assert!(matches!(
+        push("1 / 0"),
+        Err(LexingError::ExpressionDivisionByZero)
+    ));
+    assert!(matches!(
+        push("1 % (2 - 2)"),
+        Err(LexingError::ExpressionDivisionByZero)
+    ));
+    assert!(matches!(
+        push("1.5 / 0"),
+        Err(LexingError::ExpressionDivisionByZero)
+    ));
+}
+
+#[test]
+fn bitwise_operators_need_integers() {
+    assert!(matches!(
+        push("1.5 | 1"),
+        Err(LexingError::NonIntegerOperand)
+    ));
+    assert!(matches!(push("~0.5"), Err(LexingError::NonIntegerOperand)));
+    assert!(matches!(
+        push("1 << 0.5"),
+        Err(LexingError::NonIntegerOperand)
+    ));
+}
+
+#[test]
+fn undefined_symbols_are_an_error() {
+    assert!(matches!(
+        push("missing + 1"),
+        Err(LexingError::UndefinedSymbol(name)) if name == "missing"
+    ));
+}
+
+#[test]
+fn malformed_expressions_are_an_error() {
+    for expression in ["1 +", "(1", "1)", "1 2", "* 2", "()"] {
+        assert!(
+            matches!(push(expression), Err(LexingError::InvalidExpression)),
+            "{}",
+            expression
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    env,
+    ffi::{c_char, c_void},
+    fs,
+    path::PathBuf,
+    process::Command,
+};
+
+use uvm::ffi::{self, UvmOutput, UvmVm};
+
+const HEADER: &str = "include/uvm.h";
+
+// a declaration in the header with the comment above it.
+struct Item {
+    name: &'static str,
+    comment: Vec<&'static str>,
+    declaration: String,
+}
+
+// turns a table of the c api into the header's items. every entry is checked against the
+// item of the same name in src/ffi.rs, so a signature that no longer matches does not compile.
+macro_rules! api {
+    (@items [$($items:expr,)*]) => {
+        vec![$($items,)*]
+    };
+    (@items [$($items:expr,)*] $(#[doc = $comment:literal])* const $name:ident; $($rest:tt)*) => {
+        api!(@items [$($items,)* Item {
+            name: stringify!($name),
+            comment: vec![$($comment),*],
+            declaration: constant(stringify!($name), ffi::$name),
+        },] $($rest)*)
+    };
+    (@items [$($items:expr,)*] $(#[doc = $comment:literal])* struct $name:ident; $($rest:tt)*) => {
+        api!(@items [$($items,)* Item {
+            name: stringify!($name),
+            comment: vec![$($comment),*],
+            declaration: {
+                let _: Option<&$name> = None;
+                format!("typedef struct {0} {0};", stringify!($name))
+            },
+        },] $($rest)*)
+    };
+    (@items [$($items:expr,)*] $(#[doc = $comment:literal])*
+        type $name:ident = fn($($parameter:ident: $type:ty),*); $($rest:tt)*) => {
+        api!(@items [$($items,)* Item {
+            name: stringify!($name),
+            comment: vec![$($comment),*],
+            declaration: {
+                let _: fn($name) -> unsafe extern "C" fn($($type),*) = |function| function;
+                format!(
+                    "typedef void (*{})({});",
+                    stringify!($name),
+                    c_parameters(&[$((stringify!($parameter), stringify!($type))),*])
+                )
+            },
+        },] $($rest)*)
+    };
+    (@items [$($items:expr,)*] $(#[doc = $comment:literal])*
+        fn $name:ident($($parameter:ident: $type:ty),*) $(-> $returns:ty)?; $($rest:tt)*) => {
+        api!(@items [$($items,)* Item {
+            name: stringify!($name),
+            comment: vec![$($comment),*],
+            declaration: {
+                let _: unsafe extern "C" fn($($type),*) $(-> $returns)? = ffi::$name;
+                function(
+                    stringify!($name),
+                    &[$((stringify!($parameter), stringify!($type))),*],
+                    concat!("" $(, stringify!($returns))?),
+                )
+            },
+        },] $($rest)*)
+    };
+    ($($table:tt)*) => {
+        api!(@items [] $($table)*)
+    };
+}
+
+// the c api in the order of the header, with the comments it gets there.
+fn items() -> Vec<Item> {
+    api! {
+        const UVM_OK;
+        const UVM_HALTED;
+        const UVM_PAUSED;
+        const UVM_CANCELLED;
+        const UVM_TIMED_OUT;
+        const UVM_TRAPPED;
+        const UVM_ERROR;
+
+        /// receives everything the program prints, `length` bytes of utf-8 at a time.
+        type UvmOutput = fn(user_data: *mut c_void, bytes: *const u8, length: usize);
+
+        struct UvmVm;
+
+        /// a new vm without a program, printing to stdout.
+        fn uvm_new() -> *mut UvmVm;
+
+        /// frees a vm; null is ignored.
+        fn uvm_free(vm: *mut UvmVm);
+
+        /// replaces the vm's program (and everything it did) with bytecode, or with assembly when the
+        /// bytes do not start with the bytecode header, and returns UVM_OK or UVM_ERROR.
+        fn uvm_load(vm: *mut UvmVm, bytes: *const u8, length: usize) -> i32;
+
+        /// executes a single instruction. returns UVM_HALTED once the program halted, UVM_PAUSED when
+        /// there is more to execute and UVM_TRAPPED when the instruction trapped.
+        fn uvm_step(vm: *mut UvmVm) -> i32;
+
+        /// executes at most `budget` instructions, or until the program stops when it is 0. returns
+        /// UVM_HALTED, UVM_PAUSED, UVM_CANCELLED, UVM_TIMED_OUT or UVM_TRAPPED.
+        fn uvm_run(vm: *mut UvmVm, budget: u64) -> i32;
+
+        /// the code given to `hlt`, once the program halted.
+        fn uvm_exit_code(vm: *const UvmVm) -> i32;
+
+        /// copies up to `capacity` values from the bottom of the stack into `values` and returns the
+        /// number of values on the stack, so a null buffer asks for the size.
+        fn uvm_stack(vm: *const UvmVm, values: *mut f64, capacity: usize) -> usize;
+
+        /// sends the program's output to `output` instead of stdout, or back to stdout when it is null.
+        fn uvm_set_output(vm: *mut UvmVm, output: Option<UvmOutput>, user_data: *mut c_void);
+
+        /// why the last call on the vm failed, or null when it did not.
+        fn uvm_last_error(vm: *const UvmVm) -> *const c_char;
+    }
+}
+
+// the c type for a rust type used in the table.
+fn c_type(rust: &str) -> &'static str {
+    match rust.replace(' ', "").as_str() {
+        "" => "void",
+        "i32" => "int32_t",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        "*mutUvmVm" => "UvmVm *",
+        "*constUvmVm" => "const UvmVm *",
+        "*constu8" => "const uint8_t *",
+        "*mutf64" => "double *",
+        "*mutc_void" => "void *",
+        "*constc_char" => "const char *",
+        "Option<UvmOutput>" => "UvmOutput",
+        _ => panic!("the table uses `{}`, which has no c type here", rust),
+    }
+}
+
+fn c_parameters(parameters: &[(&str, &str)]) -> String {
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|(name, rust)| {
+            let c = c_type(rust);
+            if c.ends_with('*') {
+                format!("{}{}", c, name)
+            } else {
+                format!("{} {}", c, name)
+            }
+        })
+        .collect();
+    if parameters.is_empty() {
+        String::from("void")
+    } else {
+        parameters.join(", ")
+    }
+}
+
+fn constant(name: &str, value: i32) -> String {
+    if value < 0 {
+        format!("#define {} ({})", name, value)
+    } else {
+        format!("#define {} {}", name, value)
+    }
+}
+
+fn function(name: &str, parameters: &[(&str, &str)], returns: &str) -> String {
+    let returns = c_type(returns);
+    let separator = if returns.ends_with('*') { "" } else { " " };
+    format!(
+        "{}{}{}({});",
+        returns,
+        separator,
+        name,
+        c_parameters(parameters)
+    )
+}
+
+fn generate_header() -> String {
+    let mut declarations = Vec::new();
+    for item in items() {
+        // constants without a comment of their own stay together.
+        let grouped = item.declaration.starts_with("#define") && item.comment.is_empty();
+        if !(declarations.is_empty() || grouped) {
+            declarations.push(String::new());
+        }
+        declarations.extend(item.comment.iter().map(|line| format!("//{}", line)));
+        declarations.push(item.declaration);
+    }
+
+    format!(
+        "// generated from the table of the api in tests/ffi.rs, run it with UVM_UPDATE_HEADER=1
+// after changing the api.
+#ifndef UVM_H
+#define UVM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {{
+#endif
+
+{}
+
+#ifdef __cplusplus
+}}
+#endif
+
+#endif
+",
+        declarations.join("\n")
+    )
+}
+
+#[test]
+fn header_matches_the_api() {
+    let header = generate_header();
+    if env::var_os("UVM_UPDATE_HEADER").is_some() {
+        fs::write(HEADER, &header).unwrap();
+    }
+    assert_eq!(
+        fs::read_to_string(HEADER).unwrap(),
+        header,
+        "{} is out of date, run the test with UVM_UPDATE_HEADER=1",
+        HEADER
+    );
+}
+
+#[test]
+fn the_table_has_every_item_of_the_api() {
+    let listed: Vec<&str> = items().iter().map(|item| item.name).collect();
+    let source = fs::read_to_string("src/ffi.rs").unwrap();
+    for line in source.lines().filter(|line| line.starts_with("pub ")) {
+        let words: Vec<&str> = line
+            .split(|current: char| !current.is_alphanumeric() && current != '_')
+            .filter(|word| !word.is_empty())
+            .collect();
+        let position = words
+            .iter()
+            .position(|word| matches!(*word, "const" | "struct" | "type" | "fn"))
+            .unwrap();
+        let name = words[position + 1];
+        assert!(listed.contains(&name), "{} is missing from the table", name);
+    }
+}
+
+// the library path is set the linux way.
+#[test]
+#[cfg(target_os = "linux")]
+fn c_programs_can_use_the_library() {
+    // cargo builds the library for the tests into the directory of the test binary.
+    let directory: PathBuf = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let executable = env::temp_dir().join(format!("uvm-ffi-{}", std::process::id()));
+
+    let compiled = Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .args([
+            "-std=c99",
+            "-Wall",
+            "-Werror",
+            "-Iinclude",
+            "tests/c/ffi.c",
+            "-o",
+        ])
+        .arg(&executable)
+        .arg(format!("-L{}", directory.display()))
+        .arg("-luvm")
+        .status()
+        .expect("a c compiler is needed to run this test");
+    assert!(compiled.success());
+
+    // cargo's own library path may hold an older build of the library.
+    let output = Command::new(&executable)
+        .env("LD_LIBRARY_PATH", &directory)
+        .output()
+        .unwrap();
+    fs::remove_file(&executable).ok();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,108 @@
+use uvm::{core::UVM, trap::Trap};
+
+fn run(source: &str) -> (UVM, Option<Trap>) {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+    let trap = vm.execute().err().map(|context| context.trap);
+    (vm, trap)
+}
+
+#[test]
+fn functions_read_arguments_and_use_locals() {
+    // sum_of_squares(3, 4) with both squares kept in locals.
+    let source = "
+push 3
+push 4
+call sum_of_squares
+hlt
+.sum_of_squares:
+enter 2
+aload 1
+dup 0
+mul
+lstore 0
+aload 0
+dup 0
+mul
+lstore 1
+lload 0
+lload 1
+add
+leave
+ret
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, None);
+    // leave drops the locals but keeps the result; the caller still owns its arguments.
+    assert_eq!(vm.stack(), &[3., 4., 25.]);
+    assert!(vm.frames().is_empty());
+}
+
+#[test]
+fn frames_nest() {
+    let source = "
+enter 1
+push 1
+lstore 0
+enter 1
+push 2
+lstore 0
+hlt
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, None);
+    assert_eq!(vm.frames().len(), 2);
+    assert_eq!(vm.frames()[1].base, 1);
+    assert_eq!(
+        vm.format_frames(),
+        "frames:\n    #0 base 0: locals [1.0]\n    #1 base 1: locals [2.0]"
+    );
+}
+
+#[test]
+fn ret_closes_frames_opened_by_the_call() {
+    let source = "
+call forgetful
+lload 0
+.forgetful:
+enter 1
+ret
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, Some(Trap::InvalidFrame));
+    assert!(vm.frames().is_empty());
+}
+
+#[test]
+fn throw_closes_frames_opened_inside_the_try() {
+    let source = "
+try @caught
+enter 3
+push 1
+throw
+@caught:
+hlt
+";
+    let (vm, trap) = run(source);
+    assert_eq!(trap, None);
+    assert!(vm.frames().is_empty());
+    assert_eq!(vm.stack(), &[1.]);
+}
+
+#[test]
+fn locals_are_bounds_checked() {
+    let (_, trap) = run("enter 1\nlload 1");
+    assert_eq!(trap, Some(Trap::IllegalOperand));
+}
+
+#[test]
+fn locals_need_a_frame() {
+    let (_, trap) = run("push 1\nlstore 0");
+    assert_eq!(trap, Some(Trap::InvalidFrame));
+}
+
+#[test]
+fn arguments_can_not_reach_below_the_stack() {
+    let (_, trap) = run("push 1\nenter 0\naload 1");
+    assert_eq!(trap, Some(Trap::StackUnderflow));
+}
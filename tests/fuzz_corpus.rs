@@ -0,0 +1,34 @@
+// runs the fuzz seed corpus (including inputs that once crashed) through the same checks as
+// the fuzz targets, so they are kept on stable without cargo-fuzz.
+use std::{fs, path::Path};
+
+#[path = "../fuzz/fuzz_targets/common.rs"]
+mod common;
+
+fn for_each_input(target: &str, check: fn(&[u8])) {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus")
+        .join(target);
+    let mut count = 0;
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        check(&fs::read(&path).unwrap());
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn assemble_corpus() {
+    for_each_input("assemble", common::check_source);
+}
+
+#[test]
+fn bytecode_corpus() {
+    for_each_input("bytecode", common::check_bytecode);
+}
+
+#[test]
+fn instructions_corpus() {
+    for_each_input("instructions", common::check_instructions);
+}
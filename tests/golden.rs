@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use uvm::{
+    core::Engine,
+    golden::{run_directory_with, Expectations, Outcome, Report},
+};
+
+#[test]
+fn golden_programs() {
+    assert!(!run_golden(Engine::Interpreter).passed.is_empty());
+}
+
+#[test]
+#[cfg(feature = "jit")]
+fn golden_programs_under_the_jit() {
+    assert_eq!(
+        run_golden(Engine::Jit).passed,
+        run_golden(Engine::Interpreter).passed
+    );
+}
+
+#[test]
+fn golden_programs_with_registers() {
+    assert_eq!(
+        run_golden(Engine::Register).passed,
+        run_golden(Engine::Interpreter).passed
+    );
+}
+
+#[test]
+fn expectations_are_read_from_comments() {
+    let source = "; expect-stdout: a\n;expect-stdout:\n; expect-error: StackUnderflow\npop";
+    assert_eq!(
+        Expectations::parse(source),
+        Ok(Expectations {
+            stdout: String::from("a\n\n"),
+            error: Some(String::from("StackUnderflow")),
+            exit_code: None,
+        })
+    );
+    assert!(Expectations::parse("; expect-exit: 300").is_err());
+}
+
+#[test]
+fn differences_are_reported() {
+    let expectations = Expectations::parse("; expect-stdout: 1\n; expect-stdout: 2").unwrap();
+    let outcome = Outcome::run("push 1\nout\npush 3\nout\nhlt 4");
+    assert_eq!(
+        outcome.compare(&expectations).unwrap(),
+        "exit: expected 0, got 4\nstdout:\n  1\n- 2\n+ 3"
+    );
+
+    let expectations = Expectations::parse("; expect-error: DivisionByZero").unwrap();
+    assert_eq!(
+        Outcome::run("pop").compare(&expectations).unwrap(),
+        "error: expected DivisionByZero, got StackUnderflow"
+    );
+}
+
+#[test]
+fn runaway_programs_are_stopped() {
+    let outcome = Outcome::run(".loop:\njmp loop");
+    assert_eq!(
+        outcome.error.as_deref(),
+        Some("LimitExceeded: Instructions")
+    );
+}
+
+// runs every program under tests/programs on `engine` and fails with each mismatch.
+fn run_golden(engine: Engine) -> Report {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let report = run_directory_with(&directory, engine).unwrap();
+    let failures: Vec<String> = report
+        .failures
+        .iter()
+        .map(|failure| format!("{}:\n{}", failure.path.display(), failure.message))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+    report
+}
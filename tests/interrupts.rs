@@ -0,0 +1,69 @@
+use std::{
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+
+use uvm::core::{RunStatus, UVM};
+
+const COUNTER: &str = "
+push 0
+.loop:
+push 1
+add
+jmp loop
+";
+
+#[test]
+fn another_thread_can_cancel_a_running_program() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(COUNTER).is_none());
+
+    let handle = vm.cancellation_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        handle.store(true, Ordering::Relaxed);
+    });
+    assert_eq!(vm.execute(), Ok(RunStatus::Cancelled));
+    canceller.join().unwrap();
+    assert!(!vm.is_halted());
+}
+
+#[test]
+fn a_passed_deadline_times_out() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(COUNTER).is_none());
+    vm.set_deadline(Some(Instant::now() + Duration::from_millis(20)));
+    assert_eq!(vm.execute(), Ok(RunStatus::TimedOut));
+}
+
+#[test]
+fn interrupted_programs_can_be_resumed() {
+    let source = "
+push 0
+.loop:
+push 1
+add
+dup 0
+push 100000
+geql
+jmpif done
+pop
+jmp loop
+.done:
+hlt
+";
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+
+    vm.set_deadline(Some(Instant::now()));
+    assert_eq!(vm.execute(), Ok(RunStatus::TimedOut));
+    vm.set_deadline(None);
+
+    vm.cancellation_handle().store(true, Ordering::Relaxed);
+    assert_eq!(vm.execute(), Ok(RunStatus::Cancelled));
+
+    // the cancellation was consumed, so the program now runs to the end.
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(vm.stack(), &[100000., 1.]);
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "jit")]
+
+mod common;
+
+use std::io;
+
+use uvm::{
+    core::{Engine, RunStatus, UVM},
+    limits::VmLimits,
+    numeric::Numeric,
+};
+
+use common::SharedBuffer;
+
+// counts down from 3000, leaving every number on the stack.
+const COUNTDOWN: &str = "
+    push 3000
+    push 1
+.loop:
+    pop
+    dup 0
+    push 1
+    sub
+    dup 0
+    not
+    not
+    jmpif loop
+    pop
+    hlt
+";
+
+#[derive(Debug, PartialEq)]
+struct State {
+    result: Result<RunStatus, String>,
+    stack: Vec<u64>,
+    instruction_pointer: usize,
+    executed: u64,
+    output: Vec<u8>,
+}
+
+fn run(source: &str, engine: Engine, setup: impl Fn(&mut UVM), budget: Option<u64>) -> State {
+    let mut vm = UVM::new();
+    vm.set_engine(engine);
+    setup(&mut vm);
+    let output = SharedBuffer::default();
+    vm.set_output(Box::new(output.clone()));
+    assert!(vm.load_program(source).is_none());
+
+    let result = match budget {
+        Some(budget) => vm.execute_for(budget),
+        None => vm.execute(),
+    };
+    let output = output.bytes();
+    State {
+        result: result.map_err(|context| context.to_string()),
+        stack: vm.stack().iter().map(|value| value.to_bits()).collect(),
+        instruction_pointer: vm.instruction_pointer(),
+        executed: vm.instructions_executed(),
+        output,
+    }
+}
+
+fn assert_same(source: &str, setup: impl Fn(&mut UVM), budget: Option<u64>) -> State {
+    let expected = run(source, Engine::Interpreter, &setup, budget);
+    assert_eq!(run(source, Engine::Jit, &setup, budget), expected);
+    expected
+}
+
+#[test]
+fn budgets_pause_at_the_same_instruction() {
+    for budget in [0, 1, 2, 7, 1023, 1024, 1025, 5000, 20_000] {
+        assert_same(COUNTDOWN, |_| {}, Some(budget));
+    }
+    let state = assert_same(COUNTDOWN, |_| {}, None);
+    assert_eq!(state.result, Ok(RunStatus::Halted(0)));
+    assert_eq!(state.stack.len(), 3001);
+}
+
+#[test]
+fn limits_stop_compiled_code() {
+    let state = assert_same(
+        COUNTDOWN,
+        |vm| {
+            vm.set_limits(VmLimits {
+                max_stack_depth: Some(700),
+                ..VmLimits::default()
+            })
+        },
+        None,
+    );
+    assert!(state.result.unwrap_err().starts_with("StackOverflow"));
+
+    let state = assert_same(
+        COUNTDOWN,
+        |vm| {
+            vm.set_limits(VmLimits {
+                max_instructions: Some(4321),
+                ..VmLimits::default()
+            })
+        },
+        None,
+    );
+    assert!(state
+        .result
+        .unwrap_err()
+        .starts_with("LimitExceeded: Instructions"));
+}
+
+#[test]
+fn traps_and_handlers_go_through_the_interpreter() {
+    let source = "
+        .trap DivisionByZero recover
+            push 1
+            push 0
+            div
+            hlt 1
+        .recover:
+            dmp
+            pop
+            pop
+            pop
+            pop
+            push nan
+            push 0
+            geql
+            out
+            push nan
+            div
+            out
+            pop
+            pop
+    ";
+    let state = assert_same(source, |_| {}, None);
+    assert!(state.result.unwrap_err().starts_with("StackUnderflow"));
+    assert!(!state.output.is_empty());
+}
+
+#[test]
+fn integer_arithmetic_is_left_to_the_interpreter() {
+    let source = "push 9007199254740992\n.loop:\npush 1\nadd\njmp loop";
+    let state = assert_same(source, |vm| vm.set_numeric(Numeric::Integer), None);
+    assert!(state.result.unwrap_err().starts_with("IntegerOverflow"));
+}
+
+#[test]
+fn changed_programs_are_compiled_again() {
+    let mut vm = UVM::new();
+    vm.set_engine(Engine::Jit);
+    vm.set_output(Box::new(io::sink()));
+    assert!(vm.load_program("push 2\npush 3\nadd\nhlt").is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(vm.stack(), [5.]);
+
+    assert!(vm.append_program("push 4\nmul\nhlt 2").is_none());
+    vm.set_instruction_pointer(4);
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(2)));
+    assert_eq!(vm.stack(), [20.]);
+}
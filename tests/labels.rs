@@ -0,0 +1,155 @@
+use uvm::{
+    core::{RunStatus, UVM},
+    error::LexingError,
+    lexer::{tokenize_line, Token, Value},
+};
+
+#[test]
+fn duplicate_global_labels_are_rejected() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program(".loop:\npush 1\n.loop:\njmp loop").unwrap();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 3);
+    assert!(matches!(&errors[0].kind, LexingError::DuplicateSymbol(name) if name == "loop"));
+}
+
+#[test]
+fn labels_can_be_referenced_before_they_are_defined() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program("jmp end\npush 1\n.end:\nhlt").is_none());
+}
+
+#[test]
+fn local_labels_are_scoped_under_the_preceding_global_label() {
+    let source = "
+.first:
+@loop:
+jmp @done
+@done:
+jmp @loop
+.second:
+@loop:
+jmp @done
+@done:
+jmp @loop
+.third:
+jmp first@loop
+";
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+
+    // every local label is used; only the global ones are never jumped to.
+    let warnings: Vec<String> = vm.warnings().iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "line 2: UnusedLabel `first`",
+            "line 7: UnusedLabel `second`",
+            "line 12: UnusedLabel `third`"
+        ]
+    );
+}
+
+#[test]
+fn duplicate_local_labels_in_one_scope_are_rejected() {
+    let mut vm = UVM::new();
+    let errors = vm
+        .load_program(".main:\n@loop:\njmp @loop\n@loop:\nhlt")
+        .unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::DuplicateSymbol(name) if name == "main@loop"));
+}
+
+#[test]
+fn local_labels_are_not_visible_from_other_scopes() {
+    let mut vm = UVM::new();
+    let errors = vm
+        .load_program(".first:\n@done:\nhlt\n.second:\njmp @done")
+        .unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedSymbol(name) if name == "second@done"));
+}
+
+#[test]
+fn numeric_labels_resolve_to_the_nearest_definition_in_each_direction() {
+    let source = "
+1:
+jmp 1f
+1:
+jmp 1b
+jmp 1f
+1:
+hlt
+";
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+}
+
+#[test]
+fn missing_numeric_label_is_an_error() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program("1:\njmp 1f").unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedSymbol(name) if name == "1f"));
+}
+
+#[test]
+fn zero_followed_by_b_is_a_backward_reference_unless_a_binary_digit_follows() {
+    assert_eq!(
+        tokenize_line("jmp 0b").unwrap(),
+        vec![
+            Token::Name("jmp".to_string()),
+            Token::NumericLabel {
+                number: 0,
+                forward: false
+            }
+        ]
+    );
+    assert_eq!(
+        tokenize_line("push 0b101").unwrap(),
+        vec![
+            Token::Name("push".to_string()),
+            Token::Number(Value::Integer(5))
+        ]
+    );
+
+    let mut vm = UVM::new();
+    assert!(vm.load_program("0:\npush 1\njmp 0b").is_none());
+}
+
+#[test]
+fn appending_only_warns_about_the_new_labels() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(".first:\nhlt").is_none());
+    assert!(vm.append_program(".second:\njmp first").is_none());
+
+    let warnings: Vec<String> = vm.warnings().iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "line 1: UnusedLabel `first`",
+            "line 1: UnusedLabel `second`"
+        ]
+    );
+}
+
+#[test]
+fn loading_replaces_the_program() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(".first:\npush 1\nhlt").is_none());
+    assert!(vm.load_program("push 2\nhlt").is_none());
+
+    assert_eq!(vm.program().len(), 2);
+    assert!(vm.symbols().is_empty());
+    assert!(vm.warnings().is_empty());
+}
+
+#[test]
+fn a_loaded_program_runs_after_a_halted_one() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program("push 1\nhlt 3").is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(3)));
+
+    assert!(vm.load_program("push 7\npush 8\nhlt").is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(vm.stack(), [7., 8.]);
+    assert_eq!(vm.instructions_executed(), 3);
+}
@@ -0,0 +1,240 @@
+use uvm::{
+    core::UVM,
+    error::LexingError,
+    lexer::{tokenize_line, Token, Value},
+};
+
+fn name(name: &str) -> Token {
+    Token::Name(name.to_string())
+}
+
+#[test]
+fn tabs_and_runs_of_whitespace_separate_tokens() {
+    let expected = vec![name("push"), Token::Number(Value::Integer(5))];
+
+    assert_eq!(tokenize_line("push 5").unwrap(), expected);
+    assert_eq!(tokenize_line("push    5").unwrap(), expected);
+    assert_eq!(tokenize_line("\tpush\t\t5\t").unwrap(), expected);
+    assert_eq!(tokenize_line("  push \t 5  ").unwrap(), expected);
+}
+
+#[test]
+fn trailing_comments_are_ignored() {
+    assert_eq!(
+        tokenize_line("push 5 ; push five").unwrap(),
+        vec![name("push"), Token::Number(Value::Integer(5))]
+    );
+    assert_eq!(tokenize_line("hlt;stop here").unwrap(), vec![name("hlt")]);
+}
+
+#[test]
+fn comment_only_lines_have_no_tokens() {
+    assert!(tokenize_line(";").unwrap().is_empty());
+    assert!(tokenize_line("; a comment").unwrap().is_empty());
+    assert!(tokenize_line("   ;indented comment").unwrap().is_empty());
+    assert!(tokenize_line("").unwrap().is_empty());
+}
+
+#[test]
+fn semicolons_inside_literals_do_not_start_comments() {
+    assert_eq!(
+        tokenize_line(".string \"a; b\" ; trailing").unwrap(),
+        vec![
+            Token::Directive("string".to_string()),
+            Token::String("a; b".to_string())
+        ]
+    );
+    assert_eq!(
+        tokenize_line("push ';'").unwrap(),
+        vec![name("push"), Token::Number(Value::Integer(';' as i64))]
+    );
+}
+
+#[test]
+fn label_definitions_are_directives_followed_by_a_colon() {
+    assert_eq!(
+        tokenize_line(".loop:").unwrap(),
+        vec![Token::Directive("loop".to_string()), Token::Colon]
+    );
+}
+
+#[test]
+fn unterminated_string_is_an_error() {
+    assert!(matches!(
+        tokenize_line(".string \"oops"),
+        Err(LexingError::IllegalLiteral)
+    ));
+}
+
+#[test]
+fn integer_literals_too_large_to_be_exact_are_read_as_floats() {
+    assert_eq!(
+        tokenize_line("push 123456789012345678").unwrap(),
+        vec![
+            name("push"),
+            Token::Number(Value::Float(123456789012345678.))
+        ]
+    );
+    assert_eq!(
+        tokenize_line("push 10000000000000000000").unwrap(),
+        vec![name("push"), Token::Number(Value::Float(1e19))]
+    );
+    assert_eq!(
+        tokenize_line("push 9007199254740992").unwrap(),
+        vec![name("push"), Token::Number(Value::Integer(1 << 53))]
+    );
+    assert!(matches!(
+        tokenize_line("push 10000000000000000000i64"),
+        Err(LexingError::ExpressionOverflow)
+    ));
+    let mut vm = UVM::new();
+    assert!(vm
+        .load_program("push 123456789012345678\npush 10000000000000000000\nhlt")
+        .is_none());
+}
+
+#[test]
+fn crlf_line_endings_are_accepted() {
+    let mut vm = UVM::new();
+    assert!(vm
+        .load_program("push 5\r\n.loop:\r\nout\r\njmp loop\r\n")
+        .is_none());
+}
+
+#[test]
+fn programs_with_irregular_spacing_and_comments_load() {
+    let source = "
+; a program
+.const  SIZE\t4
+push   SIZE * 2 ; eight
+\tdup\t0
+;
+add
+hlt   ; done
+";
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+}
+
+#[test]
+fn mnemonics_are_case_sensitive_by_default() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program("PUSH 5").unwrap();
+    assert!(matches!(errors[0].kind, LexingError::IllegalOperation));
+}
+
+#[test]
+fn mnemonics_and_directives_can_be_case_insensitive() {
+    let mut vm = UVM::new();
+    vm.set_case_insensitive(true);
+    assert!(vm
+        .load_program(".DATA\n.msg:\n.String \"hi\"\n.Text\nPush msg\nOutS\nHLT")
+        .is_none());
+}
+
+#[test]
+fn symbol_names_stay_case_sensitive() {
+    let mut vm = UVM::new();
+    vm.set_case_insensitive(true);
+    let errors = vm.load_program(".const size 4\npush SIZE").unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedSymbol(name) if name == "SIZE"));
+}
+
+// the value of a line holding nothing but one number.
+fn number(literal: &str) -> Result<Value, LexingError> {
+    match tokenize_line(literal)?.as_slice() {
+        [Token::Number(value)] => Ok(*value),
+        tokens => panic!("{} is not one number: {:?}", literal, tokens),
+    }
+}
+
+#[test]
+fn integers_can_be_written_in_other_bases_and_with_separators() {
+    assert_eq!(number("0x1F").unwrap(), Value::Integer(31));
+    assert_eq!(number("0XfF").unwrap(), Value::Integer(255));
+    assert_eq!(number("0b101").unwrap(), Value::Integer(5));
+    assert_eq!(number("0B1_0").unwrap(), Value::Integer(2));
+    assert_eq!(number("0o17").unwrap(), Value::Integer(15));
+    assert_eq!(number("1_000_000").unwrap(), Value::Integer(1_000_000));
+    assert_eq!(number("0xff_ff").unwrap(), Value::Integer(0xffff));
+}
+
+#[test]
+fn floats_have_a_point_or_an_exponent() {
+    assert_eq!(number("1.5").unwrap(), Value::Float(1.5));
+    assert_eq!(number(".5").unwrap(), Value::Float(0.5));
+    assert_eq!(number("1e3").unwrap(), Value::Float(1000.));
+    assert_eq!(number("2.5E-1").unwrap(), Value::Float(0.25));
+    assert_eq!(number("1_0.2_5").unwrap(), Value::Float(10.25));
+    assert_eq!(number("inf").unwrap(), Value::Float(f64::INFINITY));
+    assert_eq!(number("Infinity").unwrap(), Value::Float(f64::INFINITY));
+    assert!(matches!(number("nan"), Ok(Value::Float(value)) if value.is_nan()));
+}
+
+#[test]
+fn suffixes_choose_between_integers_and_floats() {
+    assert_eq!(number("5f64").unwrap(), Value::Float(5.));
+    assert_eq!(number("0o10f64").unwrap(), Value::Float(8.));
+    // `f` is a hex digit, so there a suffix is part of the number.
+    assert_eq!(number("0x10f64").unwrap(), Value::Integer(0x10f64));
+    assert_eq!(number("5.0i64").unwrap(), Value::Integer(5));
+    assert_eq!(number("1e3i64").unwrap(), Value::Integer(1000));
+    assert_eq!(number("7i64").unwrap(), Value::Integer(7));
+    assert!(matches!(
+        number("1.5i64"),
+        Err(LexingError::NonIntegerOperand)
+    ));
+    assert!(matches!(number("5u8"), Err(LexingError::IllegalLiteral)));
+}
+
+#[test]
+fn character_literals_are_their_code_points() {
+    assert_eq!(number("'A'").unwrap(), Value::Integer(65));
+    assert_eq!(number("' '").unwrap(), Value::Integer(32));
+    assert_eq!(number("'\\n'").unwrap(), Value::Integer(10));
+    assert_eq!(number("'\\t'").unwrap(), Value::Integer(9));
+    assert_eq!(number("'\\r'").unwrap(), Value::Integer(13));
+    assert_eq!(number("'\\0'").unwrap(), Value::Integer(0));
+    assert_eq!(number("'\\\\'").unwrap(), Value::Integer(92));
+    assert_eq!(number("'\\''").unwrap(), Value::Integer(39));
+    assert_eq!(number("'\"'").unwrap(), Value::Integer(34));
+    assert_eq!(number("'\\x41'").unwrap(), Value::Integer(65));
+    assert_eq!(number("'\\u{1F600}'").unwrap(), Value::Integer(0x1f600));
+    assert_eq!(number("'é'").unwrap(), Value::Integer(233));
+}
+
+#[test]
+fn malformed_literals_are_an_error() {
+    let literals = [
+        "0x",
+        "0o",
+        "0xg",
+        "0o8",
+        "0b2",
+        "1.5e",
+        "1e+",
+        "12ab",
+        "''",
+        "'ab'",
+        "'A",
+        "'\\q'",
+        "'\\x4'",
+        "'\\xzz'",
+        "'\\u41'",
+        "'\\u{}'",
+        "'\\u{41'",
+        "'\\u{110000}'",
+        "'\\u{D800}'",
+    ];
+    for literal in literals {
+        assert!(
+            matches!(tokenize_line(literal), Err(LexingError::IllegalLiteral)),
+            "{}",
+            literal
+        );
+    }
+    assert!(matches!(
+        tokenize_line(".string \"\\q\""),
+        Err(LexingError::IllegalLiteral)
+    ));
+}
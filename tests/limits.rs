@@ -0,0 +1,126 @@
+use uvm::{
+    core::UVM,
+    error::BytecodeError,
+    limits::{Limit, VmLimits},
+    trap::Trap,
+};
+
+fn run_limited(source: &str, limits: VmLimits) -> (UVM, Option<Trap>) {
+    let mut vm = UVM::new();
+    vm.set_limits(limits);
+    assert!(vm.load_program(source).is_none());
+    let trap = vm.execute().err().map(|context| context.trap);
+    (vm, trap)
+}
+
+#[test]
+fn endless_pushes_overflow_the_stack() {
+    let limits = VmLimits {
+        max_stack_depth: Some(100),
+        ..VmLimits::default()
+    };
+    let (vm, trap) = run_limited(".loop:\npush 1\njmp loop", limits);
+    assert_eq!(trap, Some(Trap::StackOverflow));
+    assert_eq!(vm.stack().len(), 100);
+}
+
+#[test]
+fn frame_locals_count_towards_the_stack() {
+    let limits = VmLimits {
+        max_stack_depth: Some(4),
+        ..VmLimits::default()
+    };
+    let (vm, trap) = run_limited("push 1\nenter 4\nhlt", limits);
+    assert_eq!(trap, Some(Trap::StackOverflow));
+    assert_eq!(vm.stack(), &[1.]);
+    assert!(vm.frames().is_empty());
+}
+
+#[test]
+fn endless_recursion_exceeds_the_call_depth() {
+    let limits = VmLimits {
+        max_call_depth: Some(16),
+        ..VmLimits::default()
+    };
+    let (_, trap) = run_limited(".recurse:\ncall recurse", limits);
+    assert_eq!(trap, Some(Trap::LimitExceeded(Limit::CallDepth)));
+}
+
+#[test]
+fn endless_loops_exceed_the_instruction_count() {
+    let limits = VmLimits {
+        max_instructions: Some(1000),
+        ..VmLimits::default()
+    };
+    let (vm, trap) = run_limited(".loop:\njmp loop", limits);
+    assert_eq!(trap, Some(Trap::LimitExceeded(Limit::Instructions)));
+    assert_eq!(vm.instructions_executed(), 1000);
+}
+
+#[test]
+fn frames_and_handlers_count_towards_the_stack() {
+    let limits = VmLimits {
+        max_stack_depth: Some(10),
+        ..VmLimits::default()
+    };
+    let (vm, trap) = run_limited(".loop:\nenter 0\njmp loop", limits);
+    assert_eq!(trap, Some(Trap::StackOverflow));
+    assert_eq!(vm.frames().len(), 10);
+
+    let (vm, trap) = run_limited("push 1\n.loop:\ntry loop\njmp loop", limits);
+    assert_eq!(trap, Some(Trap::StackOverflow));
+    assert_eq!(vm.stack(), &[1.]);
+    assert_eq!(vm.instructions_executed(), 19);
+}
+
+#[test]
+fn data_must_fit_in_memory() {
+    let mut vm = UVM::new();
+    vm.set_limits(VmLimits {
+        max_memory_bytes: Some(8),
+        ..VmLimits::default()
+    });
+    let source = ".data\n.string \"short\"\n.string \"far too long\"\n.text\nhlt";
+    let errors = vm.load_program(source).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), "line 3: LimitExceeded: Memory");
+
+    let mut assembled = UVM::new();
+    assert!(assembled.load_program(source).is_none());
+    assert_eq!(
+        vm.load_bytecode(&assembled.to_bytecode()),
+        Some(BytecodeError::LimitExceeded(Limit::Memory))
+    );
+}
+
+#[test]
+fn output_stops_before_the_limit_is_crossed() {
+    let limits = VmLimits {
+        max_output_bytes: Some(5),
+        ..VmLimits::default()
+    };
+    let (vm, trap) = run_limited("push 7\n.loop:\nout\njmp loop", limits);
+    assert_eq!(trap, Some(Trap::LimitExceeded(Limit::Output)));
+    assert_eq!(vm.output_bytes(), 4);
+}
+
+#[test]
+fn limits_can_not_be_handled() {
+    let source = "
+.trap StackUnderflow on_trap
+.loop:
+push 1
+jmp loop
+.on_trap:
+hlt
+";
+    let limits = VmLimits {
+        max_stack_depth: Some(10),
+        ..VmLimits::default()
+    };
+    let (_, trap) = run_limited(source, limits);
+    assert_eq!(trap, Some(Trap::StackOverflow));
+
+    let mut vm = UVM::new();
+    assert!(vm.load_program(".trap StackOverflow x\n.x:\nhlt").is_some());
+}
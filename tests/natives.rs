@@ -0,0 +1,81 @@
+use std::{cell::RefCell, rc::Rc};
+
+use uvm::{
+    core::{RunStatus, UVM},
+    error::LexingError,
+    trap::Trap,
+};
+
+#[test]
+fn natives_pop_their_arguments_and_push_their_results() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut vm = UVM::new();
+    vm.register_native("divmod", 2, 2, |args| {
+        Ok(vec![(args[0] / args[1]).floor(), args[0] % args[1]])
+    });
+    let recorder = Rc::clone(&seen);
+    vm.register_native("record", 1, 0, move |args| {
+        recorder.borrow_mut().push(args[0]);
+        Ok(Vec::new())
+    });
+
+    assert!(vm
+        .load_program("push 17\npush 5\nnative divmod\nnative record\nnative record\nhlt")
+        .is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(*seen.borrow(), vec![2., 3.]);
+}
+
+#[test]
+fn natives_can_be_called_by_id() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut vm = UVM::new();
+    let recorder = Rc::clone(&seen);
+    let id = vm.register_native("record", 1, 0, move |args| {
+        recorder.borrow_mut().push(args[0]);
+        Ok(Vec::new())
+    });
+
+    let source = format!("push 42\ncallnative {}\nhlt", id);
+    assert!(vm.load_program(&source).is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(*seen.borrow(), vec![42.]);
+}
+
+#[test]
+fn failing_natives_stop_the_program_with_their_message() {
+    let mut vm = UVM::new();
+    vm.register_native("fail", 0, 0, |_| Err("out of paper".to_string()));
+
+    assert!(vm.load_program("native fail\nhlt").is_none());
+    let context = vm.execute().unwrap_err();
+    assert_eq!(context.trap.to_string(), "NativeError: fail: out of paper");
+}
+
+#[test]
+fn natives_must_return_the_declared_number_of_results() {
+    let mut vm = UVM::new();
+    vm.register_native("pair", 0, 2, |_| Ok(vec![1.]));
+
+    assert!(vm.load_program("native pair\nhlt").is_none());
+    assert!(matches!(
+        vm.execute().unwrap_err().trap,
+        Trap::NativeError(_)
+    ));
+}
+
+#[test]
+fn natives_need_enough_arguments_on_the_stack() {
+    let mut vm = UVM::new();
+    vm.register_native("sum", 2, 1, |args| Ok(vec![args[0] + args[1]]));
+
+    assert!(vm.load_program("push 1\nnative sum\nhlt").is_none());
+    assert_eq!(vm.execute().unwrap_err().trap, Trap::StackUnderflow);
+}
+
+#[test]
+fn unknown_natives_are_assembly_errors() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program("native missing").unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedNative(name) if name == "missing"));
+}
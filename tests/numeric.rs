@@ -0,0 +1,89 @@
+mod common;
+
+use uvm::{
+    core::{RunStatus, UVM},
+    numeric::Numeric,
+    trap::Trap,
+};
+
+use common::SharedBuffer;
+
+fn run_integer(source: &str) -> (UVM, Result<RunStatus, Trap>) {
+    let mut vm = UVM::new();
+    vm.set_numeric(Numeric::Integer);
+    assert!(vm.load_program(source).is_none());
+    let result = vm.execute().map_err(|context| context.trap);
+    (vm, result)
+}
+
+#[test]
+fn floats_are_the_default() {
+    let mut vm = UVM::new();
+    assert_eq!(vm.numeric(), Numeric::Float);
+    assert!(vm.load_program("push 7\npush 2\ndiv\nhlt").is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(vm.stack(), &[3.5]);
+}
+
+#[test]
+fn integer_arithmetic_truncates_division() {
+    let (vm, result) = run_integer("push 7\npush 2\ndiv\npush -7\npush 2\ndiv\nhlt");
+    assert_eq!(result, Ok(RunStatus::Halted(0)));
+    assert_eq!(vm.stack(), &[3., -3.]);
+}
+
+#[test]
+fn integer_arithmetic_traps_instead_of_losing_precision() {
+    let (vm, result) = run_integer("push 0x20000000000000\npush 1\nadd");
+    assert_eq!(result, Err(Trap::IntegerOverflow));
+    // the operands are left for a handler.
+    assert_eq!(vm.stack(), &[9007199254740992., 1.]);
+
+    let (_, result) = run_integer("push 2.5\nhlt");
+    assert_eq!(result, Err(Trap::IllegalOperand));
+    let (_, result) = run_integer("push 1\npush 0\ndiv");
+    assert_eq!(result, Err(Trap::DivisionByZero));
+}
+
+#[test]
+fn integer_overflow_can_be_handled() {
+    let (vm, result) = run_integer(
+        ".trap IntegerOverflow overflow\npush 0x10000000000000\ndup 0\nmul\nhlt\n.overflow:\nhlt 1",
+    );
+    assert_eq!(result, Ok(RunStatus::Halted(1)));
+    assert_eq!(vm.stack(), &[4503599627370496., 4503599627370496., 2., 13.]);
+}
+
+#[test]
+fn numeric_opcodes_load_like_assembly() {
+    let mut assembled = UVM::new();
+    assert!(assembled
+        .load_program("push 3\npush 4\nadd\njmpif 5\nhlt 1\nout\nhlt")
+        .is_none());
+
+    let mut vm = UVM::new();
+    let output = SharedBuffer::default();
+    vm.set_output(Box::new(output.clone()));
+    let source = "; 3 + 4\n0 3\n0 4\n19\n5 5\n29 1\n\n25 ; out\n29\n";
+    assert!(vm.load_numeric(source).is_none());
+    assert_eq!(vm.program(), assembled.program());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(output.bytes(), b"7\n");
+}
+
+#[test]
+fn numeric_opcode_errors_have_lines() {
+    let mut vm = UVM::new();
+    let errors = vm.load_numeric("0 1\n99\n0\n19 2\n4 10").unwrap();
+    let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+        errors,
+        [
+            "line 2: IllegalOperation",
+            "line 3: IllegalOperand",
+            "line 4: IllegalOperand",
+            "line 5: TargetOutOfBounds `10`",
+        ]
+    );
+    assert!(vm.program().is_empty());
+}
@@ -0,0 +1,105 @@
+use uvm::{
+    bytecode::Image,
+    core::UVM,
+    error::{BytecodeError, OperandError},
+    instruction::{Instruction, InstructionType},
+};
+
+fn first_error(source: &str) -> String {
+    let mut vm = UVM::new();
+    let errors = vm
+        .load_program(source)
+        .expect("the program should not assemble");
+    errors[0].to_string()
+}
+
+#[test]
+fn index_operands_are_checked_before_running() {
+    assert_eq!(
+        first_error("push 1\npush 2\ndup 0.5"),
+        "line 3: NonIntegralOperand `0.5`"
+    );
+    assert_eq!(
+        first_error("push 1\nswp nan"),
+        "line 2: NonFiniteOperand `NaN`"
+    );
+    assert_eq!(
+        first_error("push 1\nswp 0"),
+        "line 2: OperandOutOfRange `0.0`"
+    );
+    assert_eq!(
+        first_error("push 1\ndup 1e300"),
+        "line 2: OperandOutOfRange `1e300`"
+    );
+    assert_eq!(first_error("jmp -1"), "line 1: NegativeOperand `-1.0`");
+    assert_eq!(
+        first_error("push 1\njmpif 3.7"),
+        "line 2: NonIntegralOperand `3.7`"
+    );
+    assert_eq!(first_error("call inf"), "line 1: NonFiniteOperand `inf`");
+}
+
+#[test]
+fn jump_targets_must_be_inside_the_program() {
+    assert_eq!(first_error("jmp 2\nhlt"), "line 1: TargetOutOfBounds `2`");
+    assert_eq!(
+        first_error("try end\nhlt\n.end:"),
+        "line 1: TargetOutOfBounds `2`"
+    );
+
+    let mut vm = UVM::new();
+    assert!(vm.load_program("jmp 1\nhlt\ncall 0").is_none());
+}
+
+#[test]
+fn unresolved_operands_are_not_checked_again() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program("push 1\nswp missing").unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), "line 2: UndefinedSymbol `missing`");
+}
+
+#[test]
+fn bytecode_operands_are_checked_when_loading() {
+    let image = Image {
+        program: vec![
+            Instruction::new(InstructionType::Push, Some(1.)),
+            Instruction::new(InstructionType::Jump, Some(7.)),
+        ],
+        ..Image::default()
+    };
+    let mut vm = UVM::new();
+    assert_eq!(
+        vm.load_bytecode(&image.encode()),
+        Some(BytecodeError::InvalidOperand(
+            1,
+            OperandError::TargetOutOfBounds(7)
+        ))
+    );
+}
+
+#[test]
+fn trap_handlers_must_be_inside_the_program() {
+    assert_eq!(
+        first_error(".trap InvalidInstructionPointer 500\npush 1"),
+        "line 1: TargetOutOfBounds `500`"
+    );
+    assert_eq!(
+        first_error(".trap StackUnderflow end\npop\n.end:"),
+        "line 1: TargetOutOfBounds `1`"
+    );
+
+    let image = Image {
+        program: vec![Instruction::new(InstructionType::Push, Some(1.))],
+        trap_handlers: vec![(1, 0), (7, 1)],
+        ..Image::default()
+    };
+    let mut vm = UVM::new();
+    assert_eq!(
+        vm.load_bytecode(&image.encode()),
+        Some(BytecodeError::InvalidTrapHandler(
+            7,
+            OperandError::TargetOutOfBounds(1)
+        ))
+    );
+}
@@ -0,0 +1,315 @@
+// a deliberately simple interpreter for the same instructions as `UVM`, to check the real
+// one against. every instruction works on a copy of the machine and the copy is only kept
+// when the instruction succeeds, so a trap can never leave half an instruction behind.
+// natives and limits (other than a budget of instructions) are not modelled.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use uvm::{
+    instruction::{Instruction, InstructionType},
+    limits::Limit,
+    trap::Trap,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    base: usize,
+    locals: usize,
+    call_depth: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TryBlock {
+    handler: usize,
+    stack_depth: usize,
+    call_depth: usize,
+    frame_depth: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Machine {
+    stack: Vec<f64>,
+    calls: Vec<usize>,
+    frames: Vec<Frame>,
+    tries: Vec<TryBlock>,
+    ip: usize,
+    exit_code: Option<u8>,
+}
+
+// what running a program did: its output, the final stack and either the exit code or the
+// trap (its debug text, as nan payloads do not compare equal) and where it happened.
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub output: String,
+    pub stack: Vec<u64>,
+    pub result: Result<u8, (String, usize)>,
+}
+
+pub fn run(
+    program: &[Instruction],
+    memory: &[u8],
+    trap_handlers: &HashMap<u8, usize>,
+    budget: u64,
+) -> Outcome {
+    let mut machine = Machine::default();
+    let mut output = String::new();
+    let mut executed = 0;
+
+    let result = loop {
+        if let Some(code) = machine.exit_code {
+            break Ok(code);
+        }
+        if executed == budget {
+            let trap = Trap::LimitExceeded(Limit::Instructions);
+            break Err((format!("{:?}", trap), machine.ip));
+        }
+        executed += 1;
+
+        let address = machine.ip;
+        match step(&machine, program, memory) {
+            Ok((next, text)) => {
+                machine = next;
+                output.push_str(&text);
+            }
+            Err(trap) => match trap_handlers.get(&trap.code()) {
+                Some(handler) => {
+                    machine.stack.push(address as f64);
+                    machine.stack.push(trap.code() as f64);
+                    machine.ip = *handler;
+                }
+                None => break Err((format!("{:?}", trap), address)),
+            },
+        }
+    };
+
+    Outcome {
+        output,
+        stack: machine.stack.iter().map(|value| value.to_bits()).collect(),
+        result,
+    }
+}
+
+fn index(operand: Option<f64>) -> Result<usize, Trap> {
+    match operand {
+        Some(value) if value >= 0. && value.fract() == 0. && value < usize::MAX as f64 => {
+            Ok(value as usize)
+        }
+        _ => Err(Trap::IllegalOperand),
+    }
+}
+
+fn top(stack: &[f64]) -> Result<f64, Trap> {
+    stack.last().copied().ok_or(Trap::StackUnderflow)
+}
+
+fn pop(stack: &mut Vec<f64>) -> Result<f64, Trap> {
+    stack.pop().ok_or(Trap::StackUnderflow)
+}
+
+fn flag(value: bool) -> f64 {
+    if value {
+        1.
+    } else {
+        0.
+    }
+}
+
+// executes the instruction at `machine.ip` on a copy of the machine, returning the copy and
+// what the instruction printed.
+fn step(
+    machine: &Machine,
+    program: &[Instruction],
+    memory: &[u8],
+) -> Result<(Machine, String), Trap> {
+    let instruction = program
+        .get(machine.ip)
+        .ok_or(Trap::InvalidInstructionPointer)?;
+    let mut m = machine.clone();
+    let mut text = String::new();
+    m.ip += 1;
+
+    match instruction.instruction_type {
+        InstructionType::Push => m
+            .stack
+            .push(instruction.operand.ok_or(Trap::IllegalOperand)?),
+        InstructionType::Pop => {
+            pop(&mut m.stack)?;
+        }
+        InstructionType::Duplicate => {
+            let offset = index(instruction.operand)?;
+            if offset >= m.stack.len() {
+                return Err(Trap::StackUnderflow);
+            }
+            m.stack.push(m.stack[m.stack.len() - 1 - offset]);
+        }
+        InstructionType::Swap => {
+            let offset = index(instruction.operand)?;
+            if offset == 0 {
+                return Err(Trap::IllegalOperand);
+            }
+            if offset >= m.stack.len() {
+                return Err(Trap::StackUnderflow);
+            }
+            let top = m.stack.len() - 1;
+            m.stack.swap(top, top - offset);
+        }
+
+        InstructionType::Jump => m.ip = index(instruction.operand)?,
+        InstructionType::JumpIf => {
+            let condition = top(&m.stack)?;
+            let target = index(instruction.operand)?;
+            if condition != 0. {
+                m.ip = target;
+            }
+        }
+        InstructionType::Call => {
+            m.ip = index(instruction.operand)?;
+            m.calls.push(machine.ip + 1);
+        }
+        InstructionType::Return => {
+            m.ip = m.calls.pop().ok_or(Trap::CallStackUnderflow)?;
+            let depth = m.calls.len();
+            m.tries.retain(|block| block.call_depth <= depth);
+            m.frames.retain(|frame| frame.call_depth <= depth);
+        }
+
+        InstructionType::Enter => {
+            let locals = index(instruction.operand)?;
+            m.frames.push(Frame {
+                base: m.stack.len(),
+                locals,
+                call_depth: m.calls.len(),
+            });
+            m.stack.resize(m.stack.len() + locals, 0.);
+        }
+        InstructionType::Leave => {
+            let frame = m.frames.pop().ok_or(Trap::InvalidFrame)?;
+            let end = (frame.base + frame.locals).min(m.stack.len());
+            m.stack.drain(frame.base.min(end)..end);
+        }
+        InstructionType::LoadLocal | InstructionType::StoreLocal => {
+            let frame = *m.frames.last().ok_or(Trap::InvalidFrame)?;
+            let local = index(instruction.operand)?;
+            if local >= frame.locals {
+                return Err(Trap::IllegalOperand);
+            }
+            let slot = frame.base + local;
+            if slot >= m.stack.len() {
+                return Err(Trap::StackUnderflow);
+            }
+            if instruction.instruction_type == InstructionType::LoadLocal {
+                m.stack.push(m.stack[slot]);
+            } else {
+                if m.stack.len() <= frame.base + frame.locals {
+                    return Err(Trap::StackUnderflow);
+                }
+                m.stack[slot] = pop(&mut m.stack)?;
+            }
+        }
+        InstructionType::LoadArgument => {
+            let frame = *m.frames.last().ok_or(Trap::InvalidFrame)?;
+            let argument = index(instruction.operand)?;
+            if argument >= frame.base || frame.base - 1 - argument >= m.stack.len() {
+                return Err(Trap::StackUnderflow);
+            }
+            m.stack.push(m.stack[frame.base - 1 - argument]);
+        }
+
+        InstructionType::Try => m.tries.push(TryBlock {
+            handler: index(instruction.operand)?,
+            stack_depth: m.stack.len(),
+            call_depth: m.calls.len(),
+            frame_depth: m.frames.len(),
+        }),
+        InstructionType::EndTry => {
+            m.tries.pop().ok_or(Trap::IllegalOperation)?;
+        }
+        InstructionType::Throw => {
+            let value = top(&m.stack)?;
+            let block = m.tries.pop().ok_or(Trap::UncaughtThrow(value))?;
+            m.stack.truncate(block.stack_depth);
+            m.calls.truncate(block.call_depth);
+            m.frames.truncate(block.frame_depth);
+            m.stack.push(value);
+            m.ip = block.handler;
+        }
+
+        InstructionType::Equal
+        | InstructionType::GreaterEqual
+        | InstructionType::Add
+        | InstructionType::Subtract
+        | InstructionType::Multiply
+        | InstructionType::Divide => {
+            if m.stack.len() < 2 {
+                return Err(Trap::StackUnderflow);
+            }
+            let b = pop(&mut m.stack)?;
+            let a = pop(&mut m.stack)?;
+            m.stack.push(match instruction.instruction_type {
+                InstructionType::Equal => flag(a == b),
+                InstructionType::GreaterEqual => flag(a >= b),
+                InstructionType::Add => a + b,
+                InstructionType::Subtract => a - b,
+                InstructionType::Multiply => a * b,
+                _ if b == 0. => return Err(Trap::DivisionByZero),
+                _ => a / b,
+            });
+        }
+        InstructionType::Not => {
+            let a = pop(&mut m.stack)?;
+            m.stack.push(flag(a == 0.));
+        }
+
+        InstructionType::CallNative => return Err(Trap::IllegalOperand),
+
+        InstructionType::Dump => {
+            text = format!("stack: {:#?}\n", m.stack);
+            if !m.frames.is_empty() {
+                text.push_str("frames:");
+                for (number, frame) in m.frames.iter().enumerate() {
+                    let end = (frame.base + frame.locals).min(m.stack.len());
+                    let locals = &m.stack[frame.base.min(end)..end];
+                    text.push_str(&format!(
+                        "\n    #{} base {}: locals {:?}",
+                        number, frame.base, locals
+                    ));
+                }
+                text.push('\n');
+            }
+        }
+        InstructionType::Output => text = format!("{}\n", top(&m.stack)?),
+        InstructionType::Outputf => text = format!("{:.15}\n", top(&m.stack)?),
+        InstructionType::OutputString => {
+            let address = top(&m.stack)?;
+            if address < 0. || address.fract() != 0. || address >= memory.len() as f64 {
+                return Err(Trap::InvalidMemoryAddress);
+            }
+            let bytes = &memory[address as usize..];
+            let length = bytes
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or(Trap::InvalidMemoryAddress)?;
+            text = String::from_utf8_lossy(&bytes[..length]).into_owned();
+        }
+        InstructionType::OutputCharacter => {
+            let code = top(&m.stack)?;
+            if code < 0. || code.fract() != 0. || code > u32::MAX as f64 {
+                return Err(Trap::IllegalOperand);
+            }
+            text = char::from_u32(code as u32)
+                .ok_or(Trap::IllegalOperand)?
+                .to_string();
+        }
+
+        InstructionType::Halt => {
+            m.ip = machine.ip;
+            m.exit_code = Some(match instruction.operand {
+                None => 0,
+                Some(code) if (0. ..=255.).contains(&code) && code.fract() == 0. => code as u8,
+                Some(_) => return Err(Trap::IllegalOperand),
+            });
+        }
+    }
+    Ok((m, text))
+}
@@ -0,0 +1,262 @@
+mod common;
+
+use std::io;
+
+use uvm::{
+    core::{Engine, RunStatus, UVM},
+    limits::VmLimits,
+    numeric::Numeric,
+    register::Translation,
+};
+
+use common::SharedBuffer;
+
+// counts down from 3000, leaving every number on the stack.
+const COUNTDOWN: &str = "
+    push 3000
+    push 1
+.loop:
+    pop
+    dup 0
+    push 1
+    sub
+    dup 0
+    not
+    not
+    jmpif loop
+    pop
+    hlt
+";
+
+#[derive(Debug, PartialEq)]
+struct State {
+    result: Result<RunStatus, String>,
+    stack: Vec<u64>,
+    instruction_pointer: usize,
+    executed: u64,
+    output: Vec<u8>,
+}
+
+fn run(source: &str, engine: Engine, setup: impl Fn(&mut UVM), budget: Option<u64>) -> State {
+    let mut vm = UVM::new();
+    vm.set_engine(engine);
+    setup(&mut vm);
+    let output = SharedBuffer::default();
+    vm.set_output(Box::new(output.clone()));
+    assert!(vm.load_program(source).is_none());
+
+    let result = match budget {
+        Some(budget) => vm.execute_for(budget),
+        None => vm.execute(),
+    };
+    let output = output.bytes();
+    State {
+        result: result.map_err(|context| context.to_string()),
+        stack: vm.stack().iter().map(|value| value.to_bits()).collect(),
+        instruction_pointer: vm.instruction_pointer(),
+        executed: vm.instructions_executed(),
+        output,
+    }
+}
+
+fn assert_same(source: &str, setup: impl Fn(&mut UVM), budget: Option<u64>) -> State {
+    let expected = run(source, Engine::Interpreter, &setup, budget);
+    assert_eq!(run(source, Engine::Register, &setup, budget), expected);
+    expected
+}
+
+#[test]
+fn budgets_pause_at_the_same_instruction() {
+    for budget in [0, 1, 2, 7, 1023, 1024, 1025, 5000, 20_000] {
+        assert_same(COUNTDOWN, |_| {}, Some(budget));
+    }
+    let state = assert_same(COUNTDOWN, |_| {}, None);
+    assert_eq!(state.result, Ok(RunStatus::Halted(0)));
+    assert_eq!(state.stack.len(), 3001);
+}
+
+#[test]
+fn limits_stop_translated_blocks() {
+    let state = assert_same(
+        COUNTDOWN,
+        |vm| {
+            vm.set_limits(VmLimits {
+                max_stack_depth: Some(700),
+                ..VmLimits::default()
+            })
+        },
+        None,
+    );
+    assert!(state.result.unwrap_err().starts_with("StackOverflow"));
+
+    let state = assert_same(
+        COUNTDOWN,
+        |vm| {
+            vm.set_limits(VmLimits {
+                max_instructions: Some(4321),
+                ..VmLimits::default()
+            })
+        },
+        None,
+    );
+    assert!(state
+        .result
+        .unwrap_err()
+        .starts_with("LimitExceeded: Instructions"));
+
+    // `dup` grows the stack as much as `push` does.
+    let state = assert_same(
+        "push 1\npush 2\ndup 1\ndup 0\nhlt",
+        |vm| {
+            vm.set_limits(VmLimits {
+                max_stack_depth: Some(3),
+                ..VmLimits::default()
+            })
+        },
+        None,
+    );
+    assert_eq!(
+        state.result,
+        Err(String::from("StackOverflow at 3 (dup 0)"))
+    );
+}
+
+#[test]
+fn traps_and_handlers_go_through_the_interpreter() {
+    let source = "
+        .trap DivisionByZero recover
+            push 1
+            push 0
+            div
+            hlt 1
+        .recover:
+            dmp
+            pop
+            pop
+            pop
+            pop
+            push nan
+            push 0
+            geql
+            out
+            push nan
+            div
+            out
+            pop
+            pop
+    ";
+    let state = assert_same(source, |_| {}, None);
+    assert!(state.result.unwrap_err().starts_with("StackUnderflow"));
+    assert!(!state.output.is_empty());
+}
+
+#[test]
+fn integer_arithmetic_is_left_to_the_interpreter() {
+    let source = "push 9007199254740992\n.loop:\npush 1\nadd\njmp loop";
+    let state = assert_same(source, |vm| vm.set_numeric(Numeric::Integer), None);
+    assert!(state.result.unwrap_err().starts_with("IntegerOverflow"));
+}
+
+#[test]
+fn changed_programs_are_translated_again() {
+    let mut vm = UVM::new();
+    vm.set_engine(Engine::Register);
+    vm.set_output(Box::new(io::sink()));
+    assert!(vm.load_program("push 2\npush 3\nadd\nhlt").is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert_eq!(vm.stack(), [5.]);
+
+    assert!(vm.append_program("push 4\nmul\nhlt 2").is_none());
+    vm.set_instruction_pointer(4);
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(2)));
+    assert_eq!(vm.stack(), [20.]);
+}
+
+#[test]
+fn stack_slots_become_registers() {
+    let mut vm = UVM::new();
+    let source = "
+        push 1
+        push 2
+        swp 2
+        swp 1
+        dup 2
+        push 4
+        push 0.5
+        div
+        add
+        mul
+        not
+        div
+        hlt
+    ";
+    assert!(vm.load_program(source).is_none());
+    let translation = Translation::new(vm.program(), Numeric::Float).unwrap();
+    assert_eq!(
+        translation.to_string(),
+        "\
+0..11 (needs 1):
+    r0 = s0
+    resize 3
+    s0 = 2.0
+    s1 = r0
+    s2 = 0.0
+    goto 11
+11..12 (needs 2, divisor checked):
+    r0 = s0 / s1
+    resize 1
+    s0 = r0
+    goto 12
+"
+    );
+    assert!(Translation::new(vm.program(), Numeric::Integer).is_none());
+}
+
+#[test]
+fn dumps_see_the_same_stack() {
+    let source = "
+        .trap DivisionByZero recover
+        .trap StackUnderflow underflow
+            push 7
+            push 1
+            push 2
+            swp 2
+            swp 1
+            dup 2
+            push 3
+            mul
+            dmp
+            not
+            div
+            hlt 1
+        .recover:
+            dmp
+            swp 3
+            pop
+            push 1.5
+            swp 1
+            sub
+            dmp
+            call deep
+            hlt 2
+        .deep:
+            enter 2
+            push -0
+            push 4
+            geql
+            lstore 1
+            dmp
+            pop
+            pop
+            pop
+            dup 5
+        .underflow:
+            dmp
+            hlt 3
+    ";
+    let state = assert_same(source, |_| {}, None);
+    assert_eq!(state.result, Ok(RunStatus::Halted(3)));
+    for budget in 0..60 {
+        assert_same(source, |_| {}, Some(budget));
+    }
+}
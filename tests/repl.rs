@@ -0,0 +1,90 @@
+mod common;
+
+use std::{env::temp_dir, fs};
+
+use uvm::repl::Repl;
+
+use common::SharedBuffer;
+
+fn repl() -> (Repl, SharedBuffer) {
+    let mut repl = Repl::new();
+    let output = SharedBuffer::default();
+    repl.set_output(Box::new(output.clone()));
+    (repl, output)
+}
+
+#[test]
+fn instructions_run_as_they_are_entered() {
+    let (mut repl, output) = repl();
+    assert_eq!(repl.feed("push 6"), "[6.0]");
+    assert_eq!(repl.feed("push 7"), "[6.0, 7.0]");
+    assert_eq!(repl.feed("mul"), "[42.0]");
+    assert_eq!(repl.feed("out"), "[42.0]");
+    assert_eq!(output.text(), "42\n");
+}
+
+#[test]
+fn label_blocks_are_assembled_until_an_empty_line() {
+    let (mut repl, _) = repl();
+    for line in [".square:", "dup 0", "mul", "ret"] {
+        assert_eq!(repl.feed(line), "");
+        assert_eq!(repl.prompt(), "... ");
+    }
+    assert_eq!(repl.feed(""), "ok");
+    assert_eq!(repl.prompt(), "uvm> ");
+
+    repl.feed("push 5");
+    assert_eq!(repl.feed("call square"), "[25.0]");
+    assert_eq!(repl.feed(":labels"), "square code 0");
+}
+
+#[test]
+fn failing_lines_leave_the_session_untouched() {
+    let (mut repl, output) = repl();
+    repl.feed("push 1");
+    repl.feed("out");
+    assert_eq!(repl.feed("bogus"), "LexingError: line 1: IllegalOperation");
+    assert_eq!(
+        repl.feed("push 0\nbogus"),
+        "LexingError: line 2: IllegalOperation"
+    );
+    assert!(repl.feed("div").starts_with("Trap: StackUnderflow"));
+    assert_eq!(repl.feed(":stack"), "stack: [1.0]");
+    // rebuilding the session does not repeat its output.
+    assert_eq!(output.text(), "1\n");
+}
+
+#[test]
+fn undo_and_reset() {
+    let (mut repl, _) = repl();
+    repl.feed("push 1");
+    repl.feed("push 2");
+    assert_eq!(repl.feed(":undo"), "[1.0]");
+    assert_eq!(repl.feed(":reset"), "ok");
+    assert_eq!(repl.feed(":stack"), "stack: []");
+    assert_eq!(repl.feed(":undo"), "nothing to undo");
+}
+
+#[test]
+fn halting_can_be_continued_from() {
+    let (mut repl, _) = repl();
+    assert_eq!(repl.feed("hlt 3"), "halted with 3\n[]");
+    assert_eq!(repl.feed("push 1"), "[1.0]");
+}
+
+#[test]
+fn files_can_be_loaded() {
+    let path = temp_dir().join(format!("uvm-repl-{}.uasm", std::process::id()));
+    fs::write(&path, ".double:\npush 2\nmul\nret").unwrap();
+
+    let (mut repl, _) = repl();
+    assert_eq!(
+        repl.feed(&format!(":load {}", path.display())),
+        "loaded 3 instructions"
+    );
+    repl.feed("push 21");
+    assert_eq!(repl.feed("call double"), "[42.0]");
+
+    repl.feed(":quit");
+    assert!(repl.is_finished());
+}
@@ -0,0 +1,85 @@
+use uvm::{
+    core::UVM,
+    error::LexingError,
+    symbol::{SymbolKind, Visibility},
+};
+
+const SOURCE: &str = "
+.const SIZE 4
+.data
+.message:
+.string \"hi\"
+.numbers:
+.bytes 1, 2, 3
+.text
+.export main
+.main:
+push SIZE
+@loop:
+jmp @loop
+.helper:
+hlt
+";
+
+#[test]
+fn symbols_record_kind_value_line_and_visibility() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(SOURCE).is_none());
+    let symbols = vm.symbols();
+
+    let size = symbols.get("SIZE").unwrap();
+    assert_eq!(size.kind, SymbolKind::Constant);
+    assert_eq!(size.line, 2);
+    assert_eq!(size.address(), None);
+
+    let numbers = symbols.get("numbers").unwrap();
+    assert_eq!(numbers.kind, SymbolKind::DataLabel);
+    assert_eq!(numbers.address(), Some(3));
+
+    let main = symbols.get("main").unwrap();
+    assert_eq!(main.kind, SymbolKind::CodeLabel);
+    assert_eq!(main.visibility, Visibility::Exported);
+
+    let local = symbols.get("main@loop").unwrap();
+    assert_eq!(local.visibility, Visibility::Local);
+    assert_eq!(local.address(), Some(1));
+    assert_eq!(local.line, 12);
+}
+
+#[test]
+fn labels_iterate_in_address_order() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(SOURCE).is_none());
+
+    let names: Vec<&str> = vm
+        .symbols()
+        .labels_by_address()
+        .iter()
+        .map(|symbol| symbol.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["main", "main@loop", "helper", "message", "numbers"]
+    );
+}
+
+#[test]
+fn exported_labels_are_not_reported_as_unused() {
+    let mut vm = UVM::new();
+    assert!(vm.load_program(".export entry\n.entry:\nhlt").is_none());
+    assert!(vm.warnings().is_empty());
+}
+
+#[test]
+fn exporting_an_unknown_symbol_is_an_error() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program(".export missing\nhlt").unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedSymbol(name) if name == "missing"));
+}
+
+#[test]
+fn constants_and_labels_share_one_namespace() {
+    let mut vm = UVM::new();
+    let errors = vm.load_program(".const start 1\n.start:\nhlt").unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::DuplicateSymbol(name) if name == "start"));
+}
@@ -0,0 +1,59 @@
+use uvm::{
+    core::{RunStatus, UVM},
+    error::LexingError,
+    trap::Trap,
+};
+
+#[test]
+fn unhandled_traps_report_where_they_happened() {
+    let mut vm = UVM::new();
+    assert!(vm
+        .load_program(".main:\npush 1\npush 0\ndiv\nhlt")
+        .is_none());
+
+    let context = vm.execute().unwrap_err();
+    assert_eq!(context.trap, Trap::DivisionByZero);
+    assert_eq!(context.instruction_pointer, 2);
+    assert_eq!(context.to_string(), "DivisionByZero at 2 (div) in main+2");
+    // the faulting instruction did not consume its operands.
+    assert_eq!(vm.stack(), &[1., 0.]);
+}
+
+#[test]
+fn handlers_receive_the_faulting_address_and_trap_code() {
+    let source = "
+.trap DivisionByZero on_division_by_zero
+push 1
+push 0
+div
+hlt
+.on_division_by_zero:
+hlt
+";
+    let mut vm = UVM::new();
+    assert!(vm.load_program(source).is_none());
+    assert_eq!(vm.execute(), Ok(RunStatus::Halted(0)));
+    assert!(vm.is_halted());
+    assert_eq!(
+        vm.stack(),
+        &[1., 0., 2., Trap::DivisionByZero.code() as f64]
+    );
+}
+
+#[test]
+fn handlers_only_catch_their_own_trap() {
+    let mut vm = UVM::new();
+    assert!(vm
+        .load_program(".trap DivisionByZero handler\npop\n.handler:\nhlt")
+        .is_none());
+    assert_eq!(vm.execute().unwrap_err().trap, Trap::StackUnderflow);
+}
+
+#[test]
+fn unknown_trap_names_are_assembly_errors() {
+    let mut vm = UVM::new();
+    let errors = vm
+        .load_program(".trap Oops handler\n.handler:\nhlt")
+        .unwrap();
+    assert!(matches!(&errors[0].kind, LexingError::UndefinedTrap(name) if name == "Oops"));
+}